@@ -1,8 +1,12 @@
+use crate::{feature_flags, performance_monitor};
 use dashmap::DashMap;
 /// Connection pooling functionality
 use std::collections::HashMap;
-use std::sync::atomic::{AtomicU32, Ordering};
-use std::time::{SystemTime, UNIX_EPOCH};
+use std::sync::atomic::{AtomicU32, AtomicU64, AtomicUsize, Ordering};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+/// Feature flag gating `get_connection` wait-time recording into performance_monitor
+const TIMING_FEATURE_FLAG: &str = "connection_pool_timing";
 
 #[derive(Debug, Clone)]
 pub struct Connection {
@@ -58,6 +62,39 @@ impl Connection {
     }
 }
 
+/// How many connections to an endpoint were in use each time it was sampled
+#[derive(Debug, Default)]
+struct UsageHistogram {
+    /// Index = in-use count, value = number of samples at that count
+    counts: Vec<u64>,
+    peak: usize,
+}
+
+impl UsageHistogram {
+    fn record(&mut self, in_use: usize) {
+        if self.counts.len() <= in_use {
+            self.counts.resize(in_use + 1, 0);
+        }
+        self.counts[in_use] += 1;
+        self.peak = self.peak.max(in_use);
+    }
+
+    fn to_json(&self) -> serde_json::Value {
+        let histogram: serde_json::Map<String, serde_json::Value> = self
+            .counts
+            .iter()
+            .enumerate()
+            .filter(|(_, samples)| **samples > 0)
+            .map(|(in_use, samples)| (in_use.to_string(), serde_json::json!(samples)))
+            .collect();
+        serde_json::json!({
+            "peak": self.peak,
+            "samples": self.counts.iter().sum::<u64>(),
+            "histogram": histogram
+        })
+    }
+}
+
 pub struct ConnectionPool {
     connections: DashMap<String, Connection>,
     available_connections: DashMap<String, Vec<String>>, // endpoint -> connection_ids
@@ -67,6 +104,14 @@ pub struct ConnectionPool {
     max_total_connections: u32,
     connection_timeout_ms: u64,
     max_idle_time_ms: u64,
+    /// In-use samples per endpoint, taken on every checkout and return
+    usage_histograms: DashMap<String, UsageHistogram>,
+    /// endpoint -> connection count past which no new connections are created
+    soft_limits: DashMap<String, u32>,
+    /// endpoint -> open connections, checked out or available
+    endpoint_counts: DashMap<String, AtomicUsize>,
+    /// Suffix for the next connection id, so ids are never reused
+    next_connection_id: AtomicU64,
 }
 
 impl Default for ConnectionPool {
@@ -86,10 +131,40 @@ impl ConnectionPool {
             max_total_connections: 100,
             connection_timeout_ms: 30000, // 30 seconds
             max_idle_time_ms: 300000,     // 5 minutes
+            usage_histograms: DashMap::new(),
+            soft_limits: DashMap::new(),
+            endpoint_counts: DashMap::new(),
+            next_connection_id: AtomicU64::new(0),
         }
     }
 
     pub fn get_connection(&self, endpoint: &str) -> Option<String> {
+        let started = Instant::now();
+        let acquired = self.acquire_connection(endpoint);
+        let wait_ms = started.elapsed().as_secs_f64() * 1000.0;
+
+        if feature_flags::is_feature_enabled(TIMING_FEATURE_FLAG, None) {
+            // Reuses and fresh creates have very different costs; keep them apart
+            let operation = match acquired {
+                Some((_, true)) => "get_connection_reuse",
+                Some((_, false)) => "get_connection_create",
+                None => "get_connection_exhausted",
+            };
+            performance_monitor::record_duration(
+                "connection_pool",
+                operation,
+                wait_ms,
+                acquired.is_some(),
+            );
+        }
+
+        let (connection_id, _) = acquired?;
+        self.sample_usage(endpoint);
+        Some(connection_id)
+    }
+
+    /// Check out a connection, returning its id and whether it was reused
+    fn acquire_connection(&self, endpoint: &str) -> Option<(String, bool)> {
         // Try to get an available connection
         if let Some(mut available) = self.available_connections.get_mut(endpoint) {
             if let Some(connection_id) = available.pop() {
@@ -97,7 +172,7 @@ impl ConnectionPool {
                 if let Some(mut conn) = self.connections.get_mut(&connection_id) {
                     conn.use_connection();
                     self.active_connections.fetch_add(1, Ordering::Relaxed);
-                    return Some(connection_id);
+                    return Some((connection_id, true));
                 } else {
                     // Connection was removed but still in available list - clean up
                     available.retain(|id| id != &connection_id);
@@ -106,17 +181,49 @@ impl ConnectionPool {
         }
 
         // Create new connection if within limits
-        if self.can_create_connection(endpoint) {
-            let connection_id = self.create_connection(endpoint);
-            // Double-check after creation (another thread might have created one)
-            if let Some(mut conn) = self.connections.get_mut(&connection_id) {
-                conn.use_connection();
-                self.active_connections.fetch_add(1, Ordering::Relaxed);
+        if self.reserve_connection_slot(endpoint) {
+            match self.create_connection(endpoint) {
+                Some(connection_id) => {
+                    // Double-check after creation (close_endpoint may have removed it)
+                    if let Some(mut conn) = self.connections.get_mut(&connection_id) {
+                        conn.use_connection();
+                        self.active_connections.fetch_add(1, Ordering::Relaxed);
+                        return Some((connection_id, false));
+                    }
+                }
+                // Creation failed, give the slot back
+                None => {
+                    self.total_connections.fetch_sub(1, Ordering::Relaxed);
+                    self.release_endpoint_slots(endpoint, 1);
+                }
+            }
+        }
+
+        None
+    }
+
+    /// `get_connection`, retried with exponential backoff and jitter
+    ///
+    /// Attempt `n` (from 0) waits between half and all of
+    /// `base_delay_ms * 2^n` before the next try. Gives up after
+    /// `max_attempts` and returns None.
+    pub fn get_connection_with_backoff(
+        &self,
+        endpoint: &str,
+        max_attempts: u32,
+        base_delay_ms: u64,
+    ) -> Option<String> {
+        for attempt in 0..max_attempts {
+            if let Some(connection_id) = self.get_connection(endpoint) {
                 return Some(connection_id);
-            } else {
-                // Creation failed, decrement total
-                self.total_connections.fetch_sub(1, Ordering::Relaxed);
             }
+            if attempt + 1 == max_attempts {
+                break;
+            }
+
+            let delay_ms = base_delay_ms.saturating_mul(1 << attempt.min(16));
+            let jitter_ms = rand::random::<u64>() % (delay_ms / 2 + 1);
+            std::thread::sleep(Duration::from_millis(delay_ms - delay_ms / 2 + jitter_ms));
         }
 
         None
@@ -133,12 +240,91 @@ impl ConnectionPool {
             let endpoint = connection.endpoint.clone();
 
             // Return to available pool
-            let mut available = self.available_connections.entry(endpoint).or_default();
+            let mut available = self
+                .available_connections
+                .entry(endpoint.clone())
+                .or_default();
 
             // Prevent duplicate returns
-            if !available.contains(&connection_id.to_string()) {
-                available.push(connection_id.to_string());
-                self.active_connections.fetch_sub(1, Ordering::Relaxed);
+            if available.contains(&connection_id.to_string()) {
+                return;
+            }
+            available.push(connection_id.to_string());
+            self.active_connections.fetch_sub(1, Ordering::Relaxed);
+            drop(available);
+            drop(connection);
+
+            self.sample_usage(&endpoint);
+        }
+    }
+
+    /// Record how many of an endpoint's connections are currently checked out
+    fn sample_usage(&self, endpoint: &str) {
+        let total = self.endpoint_count(endpoint);
+        let available = self
+            .available_connections
+            .get(endpoint)
+            .map(|ids| ids.len())
+            .unwrap_or(0);
+
+        self.usage_histograms
+            .entry(endpoint.to_string())
+            .or_default()
+            .record(total.saturating_sub(available));
+    }
+
+    /// Open connections for an endpoint, checked out or available
+    fn endpoint_count(&self, endpoint: &str) -> usize {
+        self.endpoint_counts
+            .get(endpoint)
+            .map_or(0, |count| count.load(Ordering::Relaxed))
+    }
+
+    /// Drop `closed` connections from an endpoint's open count
+    fn release_endpoint_slots(&self, endpoint: &str, closed: usize) {
+        if let Some(count) = self.endpoint_counts.get(endpoint) {
+            let _ = count.fetch_update(Ordering::Relaxed, Ordering::Relaxed, |open| {
+                Some(open.saturating_sub(closed))
+            });
+        }
+    }
+
+    /// Peak and distribution of in-use connections for an endpoint
+    pub fn get_usage_histogram(&self, endpoint: &str) -> HashMap<String, serde_json::Value> {
+        let histogram = self
+            .usage_histograms
+            .get(endpoint)
+            .map(|h| h.to_json())
+            .unwrap_or_else(|| UsageHistogram::default().to_json());
+
+        match histogram {
+            serde_json::Value::Object(map) => map.into_iter().collect(),
+            _ => HashMap::new(),
+        }
+    }
+
+    /// Clear usage histograms for one endpoint, or all of them
+    pub fn reset_usage_histogram(&self, endpoint: Option<&str>) {
+        match endpoint {
+            Some(endpoint) => {
+                self.usage_histograms.remove(endpoint);
+            }
+            None => self.usage_histograms.clear(),
+        }
+    }
+
+    /// Stop creating connections for an endpoint once it has `limit` of them
+    ///
+    /// Existing connections are still reused up to the hard per-endpoint
+    /// cap; past the soft limit callers get None instead of a fresh
+    /// connection. `None` removes the soft limit.
+    pub fn set_soft_limit(&self, endpoint: &str, limit: Option<u32>) {
+        match limit {
+            Some(limit) => {
+                self.soft_limits.insert(endpoint.to_string(), limit);
+            }
+            None => {
+                self.soft_limits.remove(endpoint);
             }
         }
     }
@@ -151,7 +337,100 @@ impl ConnectionPool {
             }
 
             self.total_connections.fetch_sub(1, Ordering::Relaxed);
+            self.release_endpoint_slots(&connection.endpoint, 1);
+        }
+    }
+
+    /// Close every connection (available and in use) for an endpoint
+    ///
+    /// In-use connections are marked unhealthy before removal so a late
+    /// `return_connection` won't put them back in the pool. Returns how many
+    /// connections were closed.
+    pub fn close_endpoint(&self, endpoint: &str) -> usize {
+        let endpoint_connections: Vec<String> = self
+            .connections
+            .iter_mut()
+            .filter_map(|mut entry| {
+                if entry.endpoint == endpoint {
+                    entry.is_healthy = false;
+                    Some(entry.key().clone())
+                } else {
+                    None
+                }
+            })
+            .collect();
+
+        let available = self
+            .available_connections
+            .remove(endpoint)
+            .map(|(_, ids)| ids)
+            .unwrap_or_default();
+
+        let mut closed = 0;
+        for connection_id in endpoint_connections {
+            if self.connections.remove(&connection_id).is_some() {
+                if !available.contains(&connection_id) {
+                    self.active_connections.fetch_sub(1, Ordering::Relaxed);
+                }
+                self.total_connections.fetch_sub(1, Ordering::Relaxed);
+                closed += 1;
+            }
+        }
+        self.release_endpoint_slots(endpoint, closed);
+
+        closed
+    }
+
+    /// Connection ids per endpoint, split into available and in use, with ages
+    ///
+    /// Restricted to `endpoint` when given. Useful for spotting connections
+    /// that were never returned.
+    pub fn list_connections(&self, endpoint: Option<&str>) -> HashMap<String, serde_json::Value> {
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_millis() as u64;
+
+        // Snapshot the available ids first: get_connection locks the available
+        // list before the connection, so never hold them in the other order
+        let available_ids: std::collections::HashSet<String> = self
+            .available_connections
+            .iter()
+            .flat_map(|entry| entry.value().clone())
+            .collect();
+
+        let mut listing: HashMap<String, (Vec<serde_json::Value>, Vec<serde_json::Value>)> =
+            HashMap::new();
+        for entry in self.connections.iter() {
+            let connection = entry.value();
+            if endpoint.is_some_and(|endpoint| endpoint != connection.endpoint) {
+                continue;
+            }
+
+            let is_available = available_ids.contains(&connection.id);
+            let info = serde_json::json!({
+                "id": connection.id,
+                "age_ms": now.saturating_sub(connection.created_at),
+                "idle_ms": now.saturating_sub(connection.last_used),
+            });
+
+            let (available, in_use) = listing.entry(connection.endpoint.clone()).or_default();
+            if is_available {
+                available.push(info);
+            } else {
+                in_use.push(info);
+            }
         }
+
+        listing
+            .into_iter()
+            .map(|(endpoint, (available, in_use))| {
+                (
+                    endpoint,
+                    serde_json::json!({ "available": available, "in_use": in_use }),
+                )
+            })
+            .collect()
     }
 
     pub fn health_check_connection(&self, connection_id: &str) -> bool {
@@ -216,7 +495,8 @@ impl ConnectionPool {
                 endpoint.clone(),
                 serde_json::json!({
                     "available_connections": available_count,
-                    "max_connections": self.max_connections_per_endpoint
+                    "max_connections": self.max_connections_per_endpoint,
+                    "soft_limit": self.soft_limits.get(endpoint).map(|limit| *limit)
                 }),
             );
         }
@@ -229,25 +509,48 @@ impl ConnectionPool {
         stats
     }
 
-    fn can_create_connection(&self, endpoint: &str) -> bool {
-        let total = self.total_connections.load(Ordering::Relaxed);
-        if total >= self.max_total_connections {
+    /// Reserve room for one more connection on `endpoint`
+    ///
+    /// The total and per-endpoint counts are bumped here with compare-and-swap,
+    /// before the connection exists, so concurrent callers can't both pass
+    /// the check and overshoot a limit. The soft limit counts every
+    /// connection, checked out or not.
+    fn reserve_connection_slot(&self, endpoint: &str) -> bool {
+        let max_total = self.max_total_connections;
+        if self
+            .total_connections
+            .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |total| {
+                (total < max_total).then_some(total + 1)
+            })
+            .is_err()
+        {
             return false;
         }
 
-        let endpoint_count = self
-            .available_connections
-            .get(endpoint)
-            .map(|v| v.len())
-            .unwrap_or(0) as u32;
+        let soft_limit = self.soft_limits.get(endpoint).map(|limit| *limit);
+        let cap = soft_limit.map_or(self.max_connections_per_endpoint, |soft_limit| {
+            soft_limit.min(self.max_connections_per_endpoint)
+        }) as usize;
+        let reserved = self
+            .endpoint_counts
+            .entry(endpoint.to_string())
+            .or_default()
+            .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |open| {
+                (open < cap).then_some(open + 1)
+            })
+            .is_ok();
 
-        endpoint_count < self.max_connections_per_endpoint
+        if !reserved {
+            self.total_connections.fetch_sub(1, Ordering::Relaxed);
+        }
+        reserved
     }
 
-    fn create_connection(&self, endpoint: &str) -> String {
+    /// Open a connection in a slot taken by `reserve_connection_slot`
+    fn create_connection(&self, endpoint: &str) -> Option<String> {
         // Validate endpoint has a valid scheme
         if !endpoint.contains("://") {
-            return String::new();
+            return None;
         }
 
         let connection_id = format!(
@@ -257,14 +560,13 @@ impl ConnectionPool {
                 .replace("/", "_")
                 .replace(":", "_")
                 .replace(".", "_"),
-            self.total_connections.load(Ordering::Relaxed)
+            self.next_connection_id.fetch_add(1, Ordering::Relaxed)
         );
 
         let connection = Connection::new(connection_id.clone(), endpoint.to_string());
         self.connections.insert(connection_id.clone(), connection);
-        self.total_connections.fetch_add(1, Ordering::Relaxed);
 
-        connection_id
+        Some(connection_id)
     }
 }
 
@@ -277,6 +579,14 @@ pub fn get_connection(endpoint: &str) -> Option<String> {
     CONNECTION_POOL.get_connection(endpoint)
 }
 
+pub fn get_connection_with_backoff(
+    endpoint: &str,
+    max_attempts: u32,
+    base_delay_ms: u64,
+) -> Option<String> {
+    CONNECTION_POOL.get_connection_with_backoff(endpoint, max_attempts, base_delay_ms)
+}
+
 pub fn return_connection(connection_id: &str) {
     CONNECTION_POOL.return_connection(connection_id);
 }
@@ -285,6 +595,14 @@ pub fn remove_connection(connection_id: &str) {
     CONNECTION_POOL.remove_connection(connection_id);
 }
 
+pub fn close_endpoint(endpoint: &str) -> usize {
+    CONNECTION_POOL.close_endpoint(endpoint)
+}
+
+pub fn list_connections(endpoint: Option<&str>) -> HashMap<String, serde_json::Value> {
+    CONNECTION_POOL.list_connections(endpoint)
+}
+
 pub fn health_check_connection(connection_id: &str) -> bool {
     CONNECTION_POOL.health_check_connection(connection_id)
 }
@@ -293,6 +611,199 @@ pub fn cleanup_expired_connections() {
     CONNECTION_POOL.cleanup_expired_connections();
 }
 
+pub fn get_usage_histogram(endpoint: &str) -> HashMap<String, serde_json::Value> {
+    CONNECTION_POOL.get_usage_histogram(endpoint)
+}
+
+pub fn reset_usage_histogram(endpoint: Option<&str>) {
+    CONNECTION_POOL.reset_usage_histogram(endpoint);
+}
+
+pub fn set_soft_limit(endpoint: &str, limit: Option<u32>) {
+    CONNECTION_POOL.set_soft_limit(endpoint, limit);
+}
+
 pub fn get_connection_pool_stats() -> HashMap<String, serde_json::Value> {
     CONNECTION_POOL.get_stats()
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ENDPOINT: &str = "https://api.example.com";
+    const OTHER: &str = "https://other.example.com";
+
+    fn stat(pool: &ConnectionPool, name: &str) -> u64 {
+        pool.get_stats()[name].as_u64().unwrap()
+    }
+
+    #[test]
+    fn test_close_endpoint() {
+        let pool = ConnectionPool::new();
+        let ids: Vec<String> = (0..3)
+            .map(|_| pool.get_connection(ENDPOINT).unwrap())
+            .collect();
+        let other = pool.get_connection(OTHER).unwrap();
+        pool.return_connection(&ids[0]);
+
+        assert_eq!(pool.close_endpoint(ENDPOINT), 3);
+        assert_eq!(stat(&pool, "total_connections"), 1);
+        assert_eq!(stat(&pool, "active_connections"), 1);
+
+        // A late return of a closed connection doesn't resurrect it
+        pool.return_connection(&ids[1]);
+        let listing = pool.list_connections(None);
+        assert!(!listing.contains_key(ENDPOINT));
+        assert_eq!(listing[OTHER]["in_use"][0]["id"], other.as_str());
+        assert_eq!(pool.close_endpoint(ENDPOINT), 0);
+    }
+
+    #[test]
+    fn test_list_connections() {
+        let pool = ConnectionPool::new();
+        let first = pool.get_connection(ENDPOINT).unwrap();
+        let second = pool.get_connection(ENDPOINT).unwrap();
+        pool.get_connection(OTHER).unwrap();
+        pool.return_connection(&first);
+
+        let listing = pool.list_connections(Some(ENDPOINT));
+        assert_eq!(listing.len(), 1);
+        let endpoint = &listing[ENDPOINT];
+        assert_eq!(endpoint["available"].as_array().unwrap().len(), 1);
+        assert_eq!(endpoint["available"][0]["id"], first.as_str());
+        assert_eq!(endpoint["in_use"].as_array().unwrap().len(), 1);
+        assert_eq!(endpoint["in_use"][0]["id"], second.as_str());
+        assert!(endpoint["in_use"][0]["age_ms"].is_u64());
+        assert!(endpoint["in_use"][0]["idle_ms"].is_u64());
+
+        assert_eq!(pool.list_connections(None).len(), 2);
+        assert!(pool.list_connections(Some("https://unknown")).is_empty());
+    }
+
+    #[test]
+    fn test_backoff_gives_up_after_max_attempts() {
+        let pool = ConnectionPool::new();
+        pool.set_soft_limit(ENDPOINT, Some(1));
+        pool.get_connection(ENDPOINT).unwrap();
+
+        let start = Instant::now();
+        assert!(pool
+            .get_connection_with_backoff(ENDPOINT, 1, 1000)
+            .is_none());
+        assert!(
+            start.elapsed() < Duration::from_millis(500),
+            "no sleep after the last attempt"
+        );
+
+        // Waits of 5-10ms then 10-20ms between three attempts
+        let start = Instant::now();
+        assert!(pool.get_connection_with_backoff(ENDPOINT, 3, 10).is_none());
+        let elapsed = start.elapsed();
+        assert!(elapsed >= Duration::from_millis(15), "{:?}", elapsed);
+        assert!(elapsed < Duration::from_millis(500), "{:?}", elapsed);
+    }
+
+    #[test]
+    fn test_backoff_succeeds_once_a_connection_frees_up() {
+        let pool = ConnectionPool::new();
+        pool.set_soft_limit(ENDPOINT, Some(1));
+        let held = pool.get_connection(ENDPOINT).unwrap();
+
+        let acquired = std::thread::scope(|scope| {
+            let waiter = scope.spawn(|| pool.get_connection_with_backoff(ENDPOINT, 10, 20));
+            std::thread::sleep(Duration::from_millis(30));
+            pool.return_connection(&held);
+            waiter.join().unwrap()
+        });
+        assert_eq!(acquired, Some(held));
+    }
+
+    #[test]
+    fn test_usage_samples_track_open_connections() {
+        let pool = ConnectionPool::new();
+        let ids: Vec<String> = (0..3)
+            .map(|_| pool.get_connection(ENDPOINT).unwrap())
+            .collect();
+        pool.return_connection(&ids[0]);
+
+        // Removing an in-use connection lowers the open count, so the next
+        // checkout (a reuse) samples 2 in use rather than 3
+        pool.remove_connection(&ids[1]);
+        assert_eq!(pool.endpoint_count(ENDPOINT), 2);
+        assert_eq!(pool.get_connection(ENDPOINT).unwrap(), ids[0]);
+
+        let histogram = pool.get_usage_histogram(ENDPOINT);
+        assert_eq!(histogram["peak"], 3);
+        assert_eq!(
+            histogram["histogram"],
+            serde_json::json!({"1": 1, "2": 3, "3": 1})
+        );
+
+        assert_eq!(pool.close_endpoint(ENDPOINT), 2);
+        assert_eq!(pool.endpoint_count(ENDPOINT), 0);
+    }
+
+    #[test]
+    fn test_timing_is_recorded_unless_disabled() {
+        let pool = ConnectionPool::new();
+        let pool_operations = |snapshot: &str| -> u64 {
+            performance_monitor::diff_performance_since(snapshot)
+                .unwrap()
+                .values()
+                .filter(|op| op["component"] == "connection_pool")
+                .map(|op| op["calls"].as_u64().unwrap())
+                .sum()
+        };
+
+        // On by default
+        let snapshot = performance_monitor::snapshot_performance();
+        pool.get_connection(ENDPOINT).unwrap();
+        assert!(pool_operations(&snapshot) >= 1);
+
+        assert!(feature_flags::set_feature_enabled(
+            TIMING_FEATURE_FLAG,
+            false
+        ));
+        let snapshot = performance_monitor::snapshot_performance();
+        pool.get_connection(ENDPOINT).unwrap();
+        let recorded = pool_operations(&snapshot);
+        assert!(feature_flags::set_feature_enabled(
+            TIMING_FEATURE_FLAG,
+            true
+        ));
+        assert_eq!(recorded, 0);
+    }
+
+    #[test]
+    fn test_concurrent_creates_respect_the_soft_limit() {
+        let pool = ConnectionPool::new();
+        pool.set_soft_limit(ENDPOINT, Some(5));
+
+        let acquired: usize = std::thread::scope(|scope| {
+            let workers: Vec<_> = (0..16)
+                .map(|_| {
+                    scope.spawn(|| {
+                        (0..20)
+                            .filter(|_| pool.get_connection(ENDPOINT).is_some())
+                            .count()
+                    })
+                })
+                .collect();
+            workers.into_iter().map(|w| w.join().unwrap()).sum()
+        });
+
+        assert_eq!(acquired, 5);
+        assert_eq!(pool.endpoint_count(ENDPOINT), 5);
+        assert_eq!(stat(&pool, "total_connections"), 5);
+        assert_eq!(pool.get_usage_histogram(ENDPOINT)["peak"], 5);
+    }
+
+    #[test]
+    fn test_failed_create_releases_its_slot() {
+        let pool = ConnectionPool::new();
+        assert!(pool.get_connection("not-a-url").is_none());
+        assert_eq!(pool.endpoint_count("not-a-url"), 0);
+        assert_eq!(stat(&pool, "total_connections"), 0);
+    }
+}
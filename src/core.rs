@@ -1,18 +1,327 @@
 use dashmap::DashMap;
+use rand::distributions::{Distribution, Standard};
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
 use serde::{Deserialize, Serialize};
 /// Core routing and load balancing functionality
-use std::collections::HashMap;
+use std::collections::{HashMap, VecDeque};
+use std::sync::{Mutex, RwLock, RwLockReadGuard};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Default cooldown applied when an endpoint is marked unhealthy
+const DEFAULT_COOLDOWN_MS: u64 = 60_000;
+
+/// Latency samples an endpoint needs before its own average is trusted
+const MIN_LATENCY_SAMPLES: u32 = 5;
+
+/// Attempts kept in each endpoint's circuit breaker window
+const BREAKER_WINDOW: usize = 20;
+/// Attempts required before the breaker evaluates the failure rate
+const BREAKER_MIN_ATTEMPTS: usize = 10;
+/// Failure rate above which the breaker opens
+const BREAKER_FAILURE_THRESHOLD: f64 = 0.5;
+/// How long an open breaker waits before allowing a half-open probe
+const BREAKER_OPEN_MS: u64 = 30_000;
+
+/// Routing strategies understood by `AdvancedRouter`
+const STRATEGIES: [&str; 5] = [
+    "simple_shuffle",
+    "least_busy",
+    "latency_based",
+    "cost_based",
+    "weighted_shuffle",
+];
+
+/// Share of the latency-derived target applied to an adaptive weight per update
+const ADAPTIVE_WEIGHT_ALPHA: f64 = 0.1;
+
+/// Share of full traffic an endpoint gets at the start of its warm-up
+const WARMUP_FLOOR: f64 = 0.1;
+
+#[derive(Debug, Clone, PartialEq, thiserror::Error)]
+pub enum RoutingError {
+    #[error("Route '{0}' not found")]
+    RouteNotFound(String),
+    #[error("No healthy endpoints for route '{route}' (cooldowns: {})", format_cooldowns(.cooldowns))]
+    NoHealthyEndpoints {
+        route: String,
+        /// (endpoint, cooldown reason) for every endpoint that was excluded
+        cooldowns: Vec<(String, String)>,
+    },
+    #[error("Only {healthy} healthy endpoints for route '{route}', {required} required")]
+    InsufficientHealthyEndpoints {
+        route: String,
+        healthy: usize,
+        required: usize,
+    },
+    #[error(
+        "All healthy endpoints for route '{route}' exceed ${ceiling}/1M output tokens \
+         (cheapest: {cheapest} at ${cheapest_cost}/1M)"
+    )]
+    CostCeilingExceeded {
+        route: String,
+        ceiling: f64,
+        cheapest: String,
+        cheapest_cost: f64,
+    },
+    #[error(
+        "All healthy endpoints for route '{route}' exceed the ${budget} request budget \
+         (cheapest: {cheapest} at ${cheapest_cost})"
+    )]
+    BudgetExceeded {
+        route: String,
+        budget: f64,
+        cheapest: String,
+        cheapest_cost: f64,
+    },
+}
+
+fn format_cooldowns(cooldowns: &[(String, String)]) -> String {
+    cooldowns
+        .iter()
+        .map(|(endpoint, reason)| format!("{}={}", endpoint, reason))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// (input, output) price per 1M tokens, from pricing data or the default fallback
+fn list_price_per_1m(model: &str) -> (f64, f64) {
+    let pricing = crate::pricing::get_pricing_data();
+    let (default_input, default_output) = crate::pricing::default_pricing_for_model(model);
+    (
+        pricing
+            .get_input_cost_per_1m(model)
+            .unwrap_or(default_input),
+        pricing
+            .get_output_cost_per_1m(model)
+            .unwrap_or(default_output),
+    )
+}
+
+/// Output price per 1M tokens, from pricing data or the default fallback
+fn output_cost_per_1m(model: &str) -> f64 {
+    list_price_per_1m(model).1
+}
+
+/// Default input:output token ratios by model family, matched as a substring
+/// of the model name ("default" applies when nothing else matches)
+const DEFAULT_COST_RATIOS: [(&str, f64); 5] = [
+    ("default", 3.0),
+    ("embedding", f64::INFINITY),
+    ("o1", 0.5),
+    ("o3", 0.5),
+    ("reasoner", 0.5),
+];
+
+/// Reason code reported by `route_with_reason` for a strategy's pick
+fn strategy_reason(strategy: &str) -> &'static str {
+    match strategy {
+        "least_busy" => "least-busy",
+        "latency_based" => "lowest-latency",
+        "cost_based" => "lowest-cost",
+        "weighted_shuffle" => "weighted-pick",
+        // simple_shuffle, and unknown strategies which fall back to it
+        _ => "random-pick",
+    }
+}
+
+fn now_ms() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_millis() as u64
+}
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct RouteConfig {
     pub strategy: String,
     pub endpoints: Vec<String>,
     pub weights: Option<Vec<f64>>,
+    /// Model served by each endpoint (aligned with `endpoints`) for pricing
+    /// lookups; an endpoint without one is looked up by its own name
+    #[serde(default)]
+    pub models: Option<Vec<String>>,
+    /// Refuse to route when fewer endpoints than this are healthy
+    #[serde(default)]
+    pub min_healthy: Option<usize>,
+    /// Nudge `weighted_shuffle` weights towards lower-latency endpoints
+    #[serde(default)]
+    pub adaptive_weights: bool,
+    #[serde(default = "default_min_weight")]
+    pub min_weight: f64,
+    #[serde(default = "default_max_weight")]
+    pub max_weight: f64,
+    /// Ramp a newly added or just-recovered endpoint's share up linearly
+    /// over this many seconds (weighted_shuffle and least_busy only)
+    #[serde(default)]
+    pub warmup_seconds: Option<u64>,
+}
+
+fn default_min_weight() -> f64 {
+    0.1
+}
+
+fn default_max_weight() -> f64 {
+    10.0
+}
+
+impl RouteConfig {
+    /// Model used to price the endpoint at `index`
+    fn endpoint_model(&self, index: usize) -> &str {
+        self.models
+            .as_ref()
+            .and_then(|models| models.get(index))
+            .unwrap_or(&self.endpoints[index])
+    }
+
+    /// Configured weight for the endpoint at `index` (1.0 if unset)
+    fn base_weight(&self, index: usize) -> f64 {
+        self.weights
+            .as_ref()
+            .and_then(|weights| weights.get(index))
+            .copied()
+            .unwrap_or(1.0)
+    }
 }
 
 pub struct AdvancedRouter {
-    routes: DashMap<String, RouteConfig>,
+    /// Whole route set behind one lock so a reload is published in one swap
+    routes: RwLock<HashMap<String, RouteConfig>>,
     metrics: DashMap<String, RouteMetrics>,
+    cooldowns: DashMap<String, Cooldown>,
+    cooldown_ms: u64,
+    /// endpoint -> team -> usage, for cost attribution
+    team_usage: DashMap<String, HashMap<String, TeamUsage>>,
+    breakers: DashMap<String, CircuitBreaker>,
+    /// route -> endpoint -> current weight, for routes with `adaptive_weights`
+    adaptive_weights: DashMap<String, HashMap<String, f64>>,
+    /// Model family -> input:output token ratio used to blend list prices
+    cost_ratios: DashMap<String, f64>,
+    /// endpoint -> when it was added or last recovered, for warm-up
+    warming_since: DashMap<String, u64>,
+    /// Seeded generator for shuffle strategies; None uses the thread RNG
+    rng: Mutex<Option<StdRng>>,
+}
+
+/// Where the shuffle strategies draw their randomness from
+enum Draws<'a> {
+    /// The router's shared RNG (seeded or thread RNG)
+    Shared,
+    /// A throwaway RNG, so previews leave the shared sequence untouched
+    Preview(&'a mut StdRng),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum BreakerState {
+    Closed,
+    Open { since_ms: u64 },
+}
+
+/// Failure-rate breaker over an endpoint's most recent attempts
+///
+/// Unlike a cooldown this trips on a sustained failure rate rather than a
+/// single event. An open breaker becomes half-open after `BREAKER_OPEN_MS`
+/// and admits a single probe request; the next recorded attempt then closes
+/// it (success) or reopens it (failure). A probe that never reports back is
+/// given up on after another `BREAKER_OPEN_MS`.
+#[derive(Debug, Clone)]
+struct CircuitBreaker {
+    outcomes: VecDeque<bool>,
+    state: BreakerState,
+    /// When the half-open probe was handed out, if one is in flight
+    probe_taken_ms: Option<u64>,
+}
+
+impl CircuitBreaker {
+    fn new() -> Self {
+        Self {
+            outcomes: VecDeque::with_capacity(BREAKER_WINDOW),
+            state: BreakerState::Closed,
+            probe_taken_ms: None,
+        }
+    }
+
+    fn is_half_open(&self, now: u64) -> bool {
+        matches!(self.state, BreakerState::Open { since_ms } if now.saturating_sub(since_ms) >= BREAKER_OPEN_MS)
+    }
+
+    fn probe_in_flight(&self, now: u64) -> bool {
+        self.probe_taken_ms
+            .is_some_and(|taken| now.saturating_sub(taken) < BREAKER_OPEN_MS)
+    }
+
+    fn allows_traffic(&self, now: u64) -> bool {
+        self.state == BreakerState::Closed || (self.is_half_open(now) && !self.probe_in_flight(now))
+    }
+
+    /// Admit a request routed to this endpoint, handing out the probe if
+    /// half-open; false if another request already holds it
+    fn admit(&mut self, now: u64) -> bool {
+        if !self.allows_traffic(now) {
+            return false;
+        }
+        if self.is_half_open(now) {
+            self.probe_taken_ms = Some(now);
+        }
+        true
+    }
+
+    /// Record an attempt; returns true if it closed a half-open breaker
+    fn record(&mut self, success: bool, now: u64) -> bool {
+        if self.is_half_open(now) {
+            // Probe result decides: close with a fresh window, or reopen
+            self.outcomes.clear();
+            self.probe_taken_ms = None;
+            self.state = if success {
+                BreakerState::Closed
+            } else {
+                BreakerState::Open { since_ms: now }
+            };
+            return success;
+        }
+
+        if self.outcomes.len() == BREAKER_WINDOW {
+            self.outcomes.pop_front();
+        }
+        self.outcomes.push_back(success);
+
+        if self.state == BreakerState::Closed
+            && self.outcomes.len() >= BREAKER_MIN_ATTEMPTS
+            && self.failure_rate() > BREAKER_FAILURE_THRESHOLD
+        {
+            self.state = BreakerState::Open { since_ms: now };
+        }
+        false
+    }
+
+    fn failure_rate(&self) -> f64 {
+        if self.outcomes.is_empty() {
+            return 0.0;
+        }
+        let failures = self.outcomes.iter().filter(|success| !**success).count();
+        failures as f64 / self.outcomes.len() as f64
+    }
+
+    fn state_name(&self, now: u64) -> &'static str {
+        match self.state {
+            BreakerState::Closed => "closed",
+            BreakerState::Open { .. } if self.is_half_open(now) => "half_open",
+            BreakerState::Open { .. } => "open",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+struct TeamUsage {
+    tokens: u64,
+    requests: u64,
+}
+
+/// Why and until when an endpoint is excluded from routing
+#[derive(Debug, Clone)]
+struct Cooldown {
+    until_ms: u64,
+    reason: String,
 }
 
 #[derive(Debug, Clone)]
@@ -21,6 +330,7 @@ struct RouteMetrics {
     success_rate: f64,
     cost_per_request: f64,
     active_requests: u32,
+    latency_samples: u32,
 }
 
 impl Default for AdvancedRouter {
@@ -32,87 +342,687 @@ impl Default for AdvancedRouter {
 impl AdvancedRouter {
     pub fn new() -> Self {
         Self {
-            routes: DashMap::new(),
+            routes: RwLock::new(HashMap::new()),
             metrics: DashMap::new(),
+            cooldowns: DashMap::new(),
+            cooldown_ms: DEFAULT_COOLDOWN_MS,
+            team_usage: DashMap::new(),
+            breakers: DashMap::new(),
+            adaptive_weights: DashMap::new(),
+            cost_ratios: DEFAULT_COST_RATIOS
+                .iter()
+                .map(|(family, ratio)| (family.to_string(), *ratio))
+                .collect(),
+            warming_since: DashMap::new(),
+            rng: Mutex::new(None),
         }
     }
 
+    /// Seed the randomness used by the shuffle strategies, or go back to
+    /// unseeded randomness with `None`
+    ///
+    /// Together with the sorted candidate order (see `route_with_reason`)
+    /// this makes selection sequences reproducible, e.g. in tests.
+    pub fn set_seed(&self, seed: Option<u64>) {
+        *self.rng.lock().unwrap_or_else(|e| e.into_inner()) = seed.map(StdRng::seed_from_u64);
+    }
+
+    fn random<T>(&self, draws: &mut Draws) -> T
+    where
+        Standard: Distribution<T>,
+    {
+        if let Draws::Preview(rng) = draws {
+            return rng.gen();
+        }
+        match self.rng.lock().unwrap_or_else(|e| e.into_inner()).as_mut() {
+            Some(rng) => rng.gen(),
+            None => rand::random(),
+        }
+    }
+
+    /// A copy of the shared RNG's state, or a fresh one when unseeded
+    fn preview_rng(&self) -> StdRng {
+        match self.rng.lock().unwrap_or_else(|e| e.into_inner()).as_ref() {
+            Some(rng) => rng.clone(),
+            None => StdRng::from_entropy(),
+        }
+    }
+
+    /// Read access to the route set; routing holds this for a whole decision
+    fn routes(&self) -> RwLockReadGuard<'_, HashMap<String, RouteConfig>> {
+        self.routes.read().unwrap_or_else(|e| e.into_inner())
+    }
+
     pub fn add_route(&self, name: String, config: RouteConfig) {
-        self.routes.insert(name, config);
+        self.start_warmup(&config.endpoints);
+        self.routes
+            .write()
+            .unwrap_or_else(|e| e.into_inner())
+            .insert(name, config);
+    }
+
+    /// Start the warm-up clock for endpoints the router hasn't seen before
+    fn start_warmup(&self, endpoints: &[String]) {
+        let now = now_ms();
+        for endpoint in endpoints {
+            self.warming_since.entry(endpoint.clone()).or_insert(now);
+        }
+    }
+
+    /// Replace the whole route set, e.g. on config reload
+    ///
+    /// The new set is swapped in and stale endpoint state pruned under a
+    /// single write lock. Routing decisions hold the read lock, so each one
+    /// sees either the old set or the new one, never a mix. Endpoint state
+    /// (metrics, cooldowns, breakers) carries over for endpoints still routed
+    /// to and is dropped for the rest. Returns `(carried_over, new)`:
+    /// endpoints with and without existing metrics.
+    pub fn replace_routes(&self, routes: HashMap<String, RouteConfig>) -> (usize, usize) {
+        let endpoints: std::collections::HashSet<String> = routes
+            .values()
+            .flat_map(|config| config.endpoints.iter().cloned())
+            .collect();
+        for config in routes.values() {
+            self.start_warmup(&config.endpoints);
+        }
+
+        let mut current = self.routes.write().unwrap_or_else(|e| e.into_inner());
+        *current = routes;
+
+        self.adaptive_weights.retain(|name, _| {
+            current
+                .get(name)
+                .is_some_and(|config| config.adaptive_weights)
+        });
+
+        self.metrics
+            .retain(|endpoint, _| endpoints.contains(endpoint));
+        self.cooldowns
+            .retain(|endpoint, _| endpoints.contains(endpoint));
+        self.breakers
+            .retain(|endpoint, _| endpoints.contains(endpoint));
+        self.warming_since
+            .retain(|endpoint, _| endpoints.contains(endpoint));
+
+        let carried_over = endpoints
+            .iter()
+            .filter(|endpoint| self.metrics.contains_key(*endpoint))
+            .count();
+        drop(current);
+        (carried_over, endpoints.len() - carried_over)
+    }
+
+    /// Route set and endpoint metrics, read under one lock so they agree
+    pub fn snapshot(&self) -> serde_json::Value {
+        let routes = self.routes();
+        serde_json::json!({
+            "routes": &*routes,
+            "metrics": self.get_metrics(),
+        })
     }
 
     pub fn select_endpoint(&self, route_name: &str) -> Option<String> {
-        let route = self.routes.get(route_name)?;
+        self.route(route_name).ok()
+    }
+
+    /// Select an endpoint for a route, skipping endpoints in cooldown
+    pub fn route(&self, route_name: &str) -> Result<String, RoutingError> {
+        self.route_excluding(route_name, &[])
+    }
+
+    /// Select an endpoint for a route, also skipping the `exclude`d endpoints
+    ///
+    /// Meant for retries: pass the endpoints already tried so each attempt
+    /// lands on a different one.
+    pub fn route_excluding(
+        &self,
+        route_name: &str,
+        exclude: &[String],
+    ) -> Result<String, RoutingError> {
+        self.route_with_reason(route_name, exclude)
+            .map(|(endpoint, _)| endpoint)
+    }
 
-        match route.strategy.as_str() {
-            "simple_shuffle" => self.simple_shuffle_selection(&route),
-            "least_busy" => self.least_busy_selection(&route),
-            "latency_based" => self.latency_based_selection(&route),
-            "cost_based" => self.cost_based_selection(&route),
-            _ => self.simple_shuffle_selection(&route),
+    /// `route_excluding`, also returning a short code saying why the
+    /// endpoint was picked
+    ///
+    /// Codes are "only-healthy" when a single endpoint was eligible, or the
+    /// strategy's code ("random-pick", "least-busy", "lowest-latency",
+    /// "lowest-cost", "weighted-pick"). Cheap enough for per-request audit logs.
+    ///
+    /// Strategies see the healthy endpoints sorted by name, so among
+    /// equally scored endpoints the first by name always wins.
+    pub fn route_with_reason(
+        &self,
+        route_name: &str,
+        exclude: &[String],
+    ) -> Result<(String, &'static str), RoutingError> {
+        self.route_in(&self.routes(), route_name, exclude)
+    }
+
+    /// `route_with_reason` against an already locked route set
+    fn route_in(
+        &self,
+        routes: &HashMap<String, RouteConfig>,
+        route_name: &str,
+        exclude: &[String],
+    ) -> Result<(String, &'static str), RoutingError> {
+        let route = routes
+            .get(route_name)
+            .ok_or_else(|| RoutingError::RouteNotFound(route_name.to_string()))?;
+
+        let mut healthy = self.healthy_endpoints(&route.endpoints);
+        if let Some(required) = route.min_healthy {
+            if healthy.len() < required {
+                return Err(RoutingError::InsufficientHealthyEndpoints {
+                    route: route_name.to_string(),
+                    healthy: healthy.len(),
+                    required,
+                });
+            }
         }
+        healthy.retain(|endpoint| !exclude.contains(endpoint));
+
+        while !healthy.is_empty() {
+            // Nothing to choose between: skip the strategy entirely
+            let (endpoint, reason) = if healthy.len() == 1 {
+                (healthy[0].clone(), "only-healthy")
+            } else {
+                match self.select_with_strategy(
+                    &route.strategy,
+                    route_name,
+                    route,
+                    &healthy,
+                    &mut Draws::Shared,
+                ) {
+                    Some(endpoint) => (endpoint, strategy_reason(&route.strategy)),
+                    None => break,
+                }
+            };
+            // A half-open breaker's probe may have gone to a concurrent request
+            if self.breaker_admits(&endpoint) {
+                return Ok((endpoint, reason));
+            }
+            healthy.retain(|e| *e != endpoint);
+        }
+
+        Err(RoutingError::NoHealthyEndpoints {
+            route: route_name.to_string(),
+            cooldowns: route
+                .endpoints
+                .iter()
+                .filter_map(|endpoint| {
+                    if exclude.contains(endpoint) {
+                        return Some((endpoint.clone(), "excluded".to_string()));
+                    }
+                    self.active_cooldown(endpoint)
+                        .map(|cooldown| (endpoint.clone(), cooldown.reason))
+                        .or_else(|| {
+                            (!self.breaker_allows(endpoint))
+                                .then(|| (endpoint.clone(), "circuit_open".to_string()))
+                        })
+                })
+                .collect(),
+        })
+    }
+
+    /// Select an endpoint, skipping any whose model costs more than
+    /// `max_output_cost_per_1m` per million output tokens
+    ///
+    /// Prices come from the pricing data, falling back to the default
+    /// pricing for unknown models. If every healthy endpoint is over the
+    /// ceiling the error names the cheapest one.
+    pub fn route_with_cost_ceiling(
+        &self,
+        route_name: &str,
+        max_output_cost_per_1m: f64,
+    ) -> Result<String, RoutingError> {
+        self.route_within_price(
+            route_name,
+            max_output_cost_per_1m,
+            output_cost_per_1m,
+            |cheapest, cheapest_cost| RoutingError::CostCeilingExceeded {
+                route: route_name.to_string(),
+                ceiling: max_output_cost_per_1m,
+                cheapest,
+                cheapest_cost,
+            },
+        )
+    }
+
+    /// Select an endpoint whose estimated cost for this request stays within
+    /// `max_cost_usd`
+    ///
+    /// Output tokens are estimated per endpoint model from `max_tokens`
+    /// (falling back to the model's default output share), so a large
+    /// `max_tokens` can rule out expensive endpoints that a short reply
+    /// would fit on.
+    pub fn route_within_budget(
+        &self,
+        route_name: &str,
+        max_cost_usd: f64,
+        input_tokens: usize,
+        max_tokens: Option<usize>,
+    ) -> Result<String, RoutingError> {
+        self.route_within_price(
+            route_name,
+            max_cost_usd,
+            |model| {
+                let output_tokens = crate::tokens::estimate_output_tokens(model, max_tokens, None);
+                let (input, output) = list_price_per_1m(model);
+                (input * input_tokens as f64 + output * output_tokens as f64) / 1_000_000.0
+            },
+            |cheapest, cheapest_cost| RoutingError::BudgetExceeded {
+                route: route_name.to_string(),
+                budget: max_cost_usd,
+                cheapest,
+                cheapest_cost,
+            },
+        )
+    }
+
+    /// Route among the healthy endpoints whose model's `price` is within
+    /// `ceiling`; if none are, `over_ceiling` builds the error naming the
+    /// cheapest one
+    fn route_within_price(
+        &self,
+        route_name: &str,
+        ceiling: f64,
+        price: impl Fn(&str) -> f64,
+        over_ceiling: impl FnOnce(String, f64) -> RoutingError,
+    ) -> Result<String, RoutingError> {
+        let routes = self.routes();
+        let route = routes
+            .get(route_name)
+            .ok_or_else(|| RoutingError::RouteNotFound(route_name.to_string()))?;
+        let healthy = self.healthy_endpoints(&route.endpoints);
+        let priced: Vec<(String, f64)> = route
+            .endpoints
+            .iter()
+            .enumerate()
+            .filter(|(_, endpoint)| healthy.contains(endpoint))
+            .map(|(index, endpoint)| (endpoint.clone(), price(route.endpoint_model(index))))
+            .collect();
+
+        let (within, over): (Vec<_>, Vec<_>) =
+            priced.into_iter().partition(|(_, cost)| *cost <= ceiling);
+
+        if within.is_empty() {
+            if let Some((cheapest, cheapest_cost)) =
+                over.iter().min_by(|a, b| a.1.total_cmp(&b.1)).cloned()
+            {
+                return Err(over_ceiling(cheapest, cheapest_cost));
+            }
+        }
+
+        let excluded: Vec<String> = over.into_iter().map(|(endpoint, _)| endpoint).collect();
+        self.route_in(&routes, route_name, &excluded)
+            .map(|(endpoint, _)| endpoint)
+    }
+
+    /// What every strategy would pick for a route right now
+    ///
+    /// Runs each strategy against the current healthy endpoints without
+    /// touching any metrics. The shuffle strategies draw from a copy of the
+    /// shared RNG, so a seeded route sequence is unaffected by the preview.
+    /// Strategies with no pick map to null.
+    pub fn compare_strategies(
+        &self,
+        route_name: &str,
+    ) -> Result<HashMap<String, serde_json::Value>, RoutingError> {
+        let routes = self.routes();
+        let route = routes
+            .get(route_name)
+            .ok_or_else(|| RoutingError::RouteNotFound(route_name.to_string()))?;
+        let healthy = self.healthy_endpoints(&route.endpoints);
+
+        Ok(STRATEGIES
+            .iter()
+            .map(|strategy| {
+                let mut rng = self.preview_rng();
+                let pick = self.select_with_strategy(
+                    strategy,
+                    route_name,
+                    route,
+                    &healthy,
+                    &mut Draws::Preview(&mut rng),
+                );
+                (strategy.to_string(), serde_json::json!(pick))
+            })
+            .collect())
     }
 
-    fn simple_shuffle_selection(&self, route: &RouteConfig) -> Option<String> {
-        if route.endpoints.is_empty() {
+    fn select_with_strategy(
+        &self,
+        strategy: &str,
+        route_name: &str,
+        route: &RouteConfig,
+        endpoints: &[String],
+        draws: &mut Draws,
+    ) -> Option<String> {
+        match strategy {
+            "simple_shuffle" => self.simple_shuffle_selection(endpoints, draws),
+            "least_busy" => self.least_busy_selection(route, endpoints),
+            "latency_based" => self.latency_based_selection(endpoints, draws),
+            "cost_based" => self.cost_based_selection(route, endpoints),
+            "weighted_shuffle" => {
+                self.weighted_shuffle_selection(route_name, route, endpoints, draws)
+            }
+            _ => self.simple_shuffle_selection(endpoints, draws),
+        }
+    }
+
+    /// Put an endpoint into cooldown, recording why (defaults to "manual")
+    pub fn mark_endpoint_unhealthy(&self, endpoint: &str, reason: Option<&str>) {
+        self.cooldowns.insert(
+            endpoint.to_string(),
+            Cooldown {
+                until_ms: now_ms() + self.cooldown_ms,
+                reason: reason.unwrap_or("manual").to_string(),
+            },
+        );
+    }
+
+    /// Clear an endpoint's cooldown so it is eligible for routing again
+    pub fn mark_endpoint_healthy(&self, endpoint: &str) {
+        if self.cooldowns.remove(endpoint).is_some() {
+            self.warming_since.insert(endpoint.to_string(), now_ms());
+        }
+    }
+
+    fn active_cooldown(&self, endpoint: &str) -> Option<Cooldown> {
+        let cooldown = self.cooldowns.get(endpoint)?;
+        if cooldown.until_ms > now_ms() {
+            Some(cooldown.clone())
+        } else {
+            None
+        }
+    }
+
+    /// Record the outcome of a request attempt in the endpoint's circuit breaker
+    pub fn record_attempt(&self, endpoint: &str, success: bool) {
+        let now = now_ms();
+        let recovered = self
+            .breakers
+            .entry(endpoint.to_string())
+            .or_insert_with(CircuitBreaker::new)
+            .record(success, now);
+        if recovered {
+            self.warming_since.insert(endpoint.to_string(), now);
+        }
+    }
+
+    /// Share of full traffic an endpoint should get on a route right now
+    ///
+    /// Rises linearly from `WARMUP_FLOOR` to 1.0 over the route's
+    /// `warmup_seconds`, counted from when the endpoint was added or last
+    /// recovered (cooldown ended or breaker closed).
+    fn warmup_factor(&self, route: &RouteConfig, endpoint: &str) -> f64 {
+        let warmup_ms = match route.warmup_seconds {
+            Some(seconds) if seconds > 0 => seconds * 1000,
+            _ => return 1.0,
+        };
+
+        // An expired cooldown counts as a recovery at the time it ended
+        let now = now_ms();
+        let cooldown_ended = self
+            .cooldowns
+            .get(endpoint)
+            .map(|cooldown| cooldown.until_ms)
+            .filter(|until_ms| *until_ms <= now);
+        let since = self
+            .warming_since
+            .get(endpoint)
+            .map(|since| *since)
+            .max(cooldown_ended);
+        let Some(since) = since else {
+            return 1.0;
+        };
+
+        let progress = now.saturating_sub(since) as f64 / warmup_ms as f64;
+        (WARMUP_FLOOR + (1.0 - WARMUP_FLOOR) * progress).min(1.0)
+    }
+
+    /// Current warm-up factors for routes with `warmup_seconds`, keyed by
+    /// route then endpoint (1.0 means fully warm)
+    pub fn get_warmup_factors(&self) -> HashMap<String, serde_json::Value> {
+        self.routes()
+            .iter()
+            .filter(|(_, route)| route.warmup_seconds.is_some())
+            .map(|(name, route)| {
+                let factors: HashMap<&String, f64> = route
+                    .endpoints
+                    .iter()
+                    .map(|endpoint| (endpoint, self.warmup_factor(route, endpoint)))
+                    .collect();
+                (name.clone(), serde_json::json!(factors))
+            })
+            .collect()
+    }
+
+    /// Claim the endpoint's half-open probe if it has one; false if taken
+    fn breaker_admits(&self, endpoint: &str) -> bool {
+        self.breakers
+            .get_mut(endpoint)
+            .map(|mut breaker| breaker.admit(now_ms()))
+            .unwrap_or(true)
+    }
+
+    fn breaker_allows(&self, endpoint: &str) -> bool {
+        self.breakers
+            .get(endpoint)
+            .map(|breaker| breaker.allows_traffic(now_ms()))
+            .unwrap_or(true)
+    }
+
+    /// Endpoints not in cooldown and not behind an open breaker, sorted by
+    /// name so strategies break ties the same way regardless of config order
+    fn healthy_endpoints(&self, endpoints: &[String]) -> Vec<String> {
+        let mut healthy: Vec<String> = endpoints
+            .iter()
+            .filter(|endpoint| {
+                self.active_cooldown(endpoint).is_none() && self.breaker_allows(endpoint)
+            })
+            .cloned()
+            .collect();
+        healthy.sort_unstable();
+        healthy
+    }
+
+    fn simple_shuffle_selection(&self, endpoints: &[String], draws: &mut Draws) -> Option<String> {
+        if endpoints.is_empty() {
             return None;
         }
 
-        let index = rand::random::<usize>() % route.endpoints.len();
+        let index = self.random::<usize>(draws) % endpoints.len();
         // Note: Using modulo bias here is acceptable for endpoint selection
         // as the bias is negligible for this use case
-        Some(route.endpoints[index].clone())
+        Some(endpoints[index].clone())
     }
 
-    fn least_busy_selection(&self, route: &RouteConfig) -> Option<String> {
-        let mut best_endpoint = None;
-        let mut min_requests = u32::MAX;
+    /// Pick an endpoint with probability proportional to its weight
+    ///
+    /// Routes with `adaptive_weights` use the tuned weights, falling back to
+    /// the configured weight for endpoints that have not been tuned yet.
+    fn weighted_shuffle_selection(
+        &self,
+        route_name: &str,
+        route: &RouteConfig,
+        endpoints: &[String],
+        draws: &mut Draws,
+    ) -> Option<String> {
+        let adaptive = if route.adaptive_weights {
+            self.adaptive_weights.get(route_name)
+        } else {
+            None
+        };
 
-        for endpoint in &route.endpoints {
-            if let Some(metrics) = self.metrics.get(endpoint) {
-                if metrics.active_requests < min_requests {
-                    min_requests = metrics.active_requests;
-                    best_endpoint = Some(endpoint.clone());
-                }
-            } else {
-                // No metrics means unused endpoint, prefer it
+        let weights: Vec<f64> = endpoints
+            .iter()
+            .map(|endpoint| {
+                let base = route
+                    .endpoints
+                    .iter()
+                    .position(|e| e == endpoint)
+                    .map(|index| route.base_weight(index))
+                    .unwrap_or(1.0);
+                let weight = adaptive
+                    .as_ref()
+                    .and_then(|weights| weights.get(endpoint).copied())
+                    .unwrap_or(base)
+                    .max(0.0);
+                weight * self.warmup_factor(route, endpoint)
+            })
+            .collect();
+
+        let total: f64 = weights.iter().sum();
+        if total <= 0.0 {
+            return self.simple_shuffle_selection(endpoints, draws);
+        }
+
+        let mut point = self.random::<f64>(draws) * total;
+        for (endpoint, weight) in endpoints.iter().zip(&weights) {
+            if point < *weight {
                 return Some(endpoint.clone());
             }
+            point -= weight;
+        }
+
+        endpoints.last().cloned()
+    }
+
+    /// Pick the endpoint with the fewest active requests
+    ///
+    /// Load is counted including the request being routed and divided by
+    /// the warm-up factor, so a warming endpoint looks busier than it is.
+    fn least_busy_selection(&self, route: &RouteConfig, endpoints: &[String]) -> Option<String> {
+        let mut best_endpoint = None;
+        let mut min_load = f64::MAX;
+
+        for endpoint in endpoints {
+            // No metrics means unused endpoint
+            let active = self
+                .metrics
+                .get(endpoint)
+                .map(|metrics| metrics.active_requests)
+                .unwrap_or(0);
+            let load = (active as f64 + 1.0) / self.warmup_factor(route, endpoint);
+            if load < min_load {
+                min_load = load;
+                best_endpoint = Some(endpoint.clone());
+            }
         }
 
-        best_endpoint.or_else(|| route.endpoints.first().cloned())
+        best_endpoint.or_else(|| endpoints.first().cloned())
     }
 
-    fn latency_based_selection(&self, route: &RouteConfig) -> Option<String> {
+    /// Pick the endpoint with the lowest latency
+    ///
+    /// Endpoints with fewer than `MIN_LATENCY_SAMPLES` samples (or a zero
+    /// average) have unknown latency and are scored with the median of the
+    /// known endpoints, so a cold endpoint doesn't win on a missing or lucky
+    /// first measurement. While every endpoint is cold, picks are shuffled.
+    fn latency_based_selection(&self, endpoints: &[String], draws: &mut Draws) -> Option<String> {
+        let latencies: Vec<Option<f64>> = endpoints
+            .iter()
+            .map(|endpoint| {
+                self.metrics.get(endpoint).and_then(|metrics| {
+                    if metrics.latency_samples >= MIN_LATENCY_SAMPLES && metrics.latency_ms > 0.0 {
+                        Some(metrics.latency_ms)
+                    } else {
+                        None
+                    }
+                })
+            })
+            .collect();
+
+        let mut known: Vec<f64> = latencies.iter().flatten().copied().collect();
+        if known.is_empty() {
+            // Nothing to compare yet: spread traffic so every endpoint warms up
+            return self.simple_shuffle_selection(endpoints, draws);
+        }
+        known.sort_by(|a, b| a.total_cmp(b));
+        let median = known[known.len() / 2];
+
         let mut best_endpoint = None;
         let mut min_latency = f64::MAX;
 
-        for endpoint in &route.endpoints {
-            if let Some(metrics) = self.metrics.get(endpoint) {
-                if metrics.latency_ms < min_latency {
-                    min_latency = metrics.latency_ms;
-                    best_endpoint = Some(endpoint.clone());
-                }
+        for (endpoint, latency) in endpoints.iter().zip(latencies) {
+            let latency = latency.unwrap_or(median);
+            if latency < min_latency {
+                min_latency = latency;
+                best_endpoint = Some(endpoint.clone());
             }
         }
 
-        best_endpoint.or_else(|| route.endpoints.first().cloned())
+        best_endpoint
     }
 
-    fn cost_based_selection(&self, route: &RouteConfig) -> Option<String> {
+    /// Pick the endpoint with the lowest observed cost per request
+    ///
+    /// Before any costs have been observed, endpoints are ranked by list
+    /// price instead, blending input and output prices with the ratio for
+    /// the endpoint's model family.
+    fn cost_based_selection(&self, route: &RouteConfig, endpoints: &[String]) -> Option<String> {
         let mut best_endpoint = None;
         let mut min_cost = f64::MAX;
 
-        for endpoint in &route.endpoints {
+        for endpoint in endpoints {
             if let Some(metrics) = self.metrics.get(endpoint) {
-                if metrics.cost_per_request < min_cost {
+                if metrics.latency_samples > 0 && metrics.cost_per_request < min_cost {
                     min_cost = metrics.cost_per_request;
                     best_endpoint = Some(endpoint.clone());
                 }
             }
         }
 
-        best_endpoint.or_else(|| route.endpoints.first().cloned())
+        best_endpoint.or_else(|| {
+            endpoints
+                .iter()
+                .map(|endpoint| {
+                    let model = route
+                        .endpoints
+                        .iter()
+                        .position(|e| e == endpoint)
+                        .map(|index| route.endpoint_model(index))
+                        .unwrap_or(endpoint);
+                    (endpoint, self.blended_price_per_1m(model))
+                })
+                .min_by(|a, b| a.1.total_cmp(&b.1))
+                .map(|(endpoint, _)| endpoint.clone())
+        })
+    }
+
+    /// Set the input:output token ratio assumed for a model family
+    ///
+    /// The family matches any model whose name contains it; the longest
+    /// matching family wins. Use `f64::INFINITY` for input-only models.
+    pub fn set_cost_ratio(&self, model_family: &str, ratio: f64) {
+        self.cost_ratios
+            .insert(model_family.to_lowercase(), ratio.max(0.0));
+    }
+
+    fn cost_ratio(&self, model: &str) -> f64 {
+        let model_lower = model.to_lowercase();
+        self.cost_ratios
+            .iter()
+            .filter(|entry| entry.key() != "default" && model_lower.contains(entry.key().as_str()))
+            .max_by_key(|entry| entry.key().len())
+            .map(|entry| *entry.value())
+            .or_else(|| self.cost_ratios.get("default").map(|ratio| *ratio))
+            .unwrap_or(3.0)
+    }
+
+    /// List price per 1M tokens for a model's typical input/output mix
+    fn blended_price_per_1m(&self, model: &str) -> f64 {
+        let (input, output) = list_price_per_1m(model);
+        let ratio = self.cost_ratio(model);
+        let input_share = if ratio.is_infinite() {
+            1.0
+        } else {
+            ratio / (ratio + 1.0)
+        };
+        input * input_share + output * (1.0 - input_share)
     }
 
     pub fn update_metrics(&self, endpoint: &str, latency: f64, success: bool, cost: f64) {
@@ -120,17 +1030,151 @@ impl AdvancedRouter {
             self.metrics
                 .entry(endpoint.to_string())
                 .or_insert_with(|| RouteMetrics {
-                    latency_ms: latency,
-                    success_rate: if success { 1.0 } else { 0.0 },
-                    cost_per_request: cost,
+                    latency_ms: 0.0,
+                    success_rate: 0.0,
+                    cost_per_request: 0.0,
                     active_requests: 0,
+                    latency_samples: 0,
                 });
 
+        // First sample since creation or a reset seeds the averages
+        if metrics.latency_samples == 0 {
+            metrics.latency_ms = latency;
+            metrics.success_rate = if success { 1.0 } else { 0.0 };
+            metrics.cost_per_request = cost;
+        }
+
         // Exponential moving average: new_value * alpha + old_value * (1 - alpha)
         // Using alpha = 0.1 means new observations have 10% weight
         metrics.latency_ms = 0.1 * latency + 0.9 * metrics.latency_ms;
         metrics.success_rate = 0.1 * if success { 1.0 } else { 0.0 } + 0.9 * metrics.success_rate;
         metrics.cost_per_request = 0.1 * cost + 0.9 * metrics.cost_per_request;
+        metrics.latency_samples = metrics.latency_samples.saturating_add(1);
+        drop(metrics);
+
+        self.adjust_adaptive_weights(endpoint);
+    }
+
+    /// Move this endpoint's weight in every adaptive route towards a target
+    /// inversely proportional to its latency relative to the route average
+    fn adjust_adaptive_weights(&self, endpoint: &str) {
+        for (name, config) in self.routes().iter() {
+            if !config.adaptive_weights {
+                continue;
+            }
+            let Some(index) = config.endpoints.iter().position(|e| e == endpoint) else {
+                continue;
+            };
+
+            let latencies: Vec<f64> = config
+                .endpoints
+                .iter()
+                .filter_map(|e| self.metrics.get(e).map(|m| m.latency_ms))
+                .filter(|latency| *latency > 0.0)
+                .collect();
+            let Some(latency) = self
+                .metrics
+                .get(endpoint)
+                .map(|m| m.latency_ms)
+                .filter(|latency| *latency > 0.0)
+            else {
+                continue;
+            };
+            let mean = latencies.iter().sum::<f64>() / latencies.len() as f64;
+
+            let base = config.base_weight(index);
+            let target = (base * mean / latency).clamp(config.min_weight, config.max_weight);
+
+            let mut weights = self.adaptive_weights.entry(name.clone()).or_default();
+            let weight = weights.entry(endpoint.to_string()).or_insert(base);
+            *weight = (ADAPTIVE_WEIGHT_ALPHA * target + (1.0 - ADAPTIVE_WEIGHT_ALPHA) * *weight)
+                .clamp(config.min_weight, config.max_weight);
+        }
+    }
+
+    /// Current adaptive weights, keyed by route then endpoint
+    pub fn get_adaptive_weights(&self) -> HashMap<String, serde_json::Value> {
+        self.adaptive_weights
+            .iter()
+            .map(|entry| (entry.key().clone(), serde_json::json!(entry.value())))
+            .collect()
+    }
+
+    /// Update endpoint metrics and attribute the request's tokens to a team
+    pub fn update_metrics_tagged(
+        &self,
+        endpoint: &str,
+        latency: f64,
+        success: bool,
+        cost: f64,
+        tokens: u64,
+        team: &str,
+    ) {
+        self.update_metrics(endpoint, latency, success, cost);
+
+        let mut teams = self.team_usage.entry(endpoint.to_string()).or_default();
+        let usage = teams.entry(team.to_string()).or_default();
+        usage.tokens += tokens;
+        usage.requests += 1;
+    }
+
+    /// Zero the per-period usage counters while keeping endpoints registered
+    ///
+    /// Clears the per-team token/request totals. Latency, success rate and
+    /// cost averages (and their sample counts) are routing state rather than
+    /// counters and are kept warm, as are in-flight `active_requests`.
+    /// Unless `preserve_health` is set, cooldowns and circuit breakers are
+    /// cleared as well.
+    pub fn reset_stats(&self, preserve_health: bool) {
+        self.team_usage.clear();
+
+        if !preserve_health {
+            self.cooldowns.clear();
+            self.breakers.clear();
+        }
+    }
+
+    /// Drop volatile selection state after a live config change, so new
+    /// weights and priorities take effect right away
+    ///
+    /// Resets exactly:
+    /// - the tuned weights of every `adaptive_weights` route, so weighted
+    ///   picks go back to the configured weights and re-tune from there;
+    /// - with `restart_warmup`, the warm-up clock of every routed endpoint,
+    ///   so routes with `warmup_seconds` ramp traffic up again.
+    ///
+    /// Metrics (latency, success rate and cost averages and their sample
+    /// counts), active requests, team usage, cooldowns and circuit breakers
+    /// are untouched. No strategy keeps a cursor, so nothing else is reset.
+    pub fn rebalance_hint(&self, restart_warmup: bool) {
+        let routes = self.routes();
+        self.adaptive_weights.clear();
+
+        if restart_warmup {
+            let now = now_ms();
+            for endpoint in routes.values().flat_map(|config| &config.endpoints) {
+                self.warming_since.insert(endpoint.clone(), now);
+            }
+        }
+    }
+
+    /// Per-team token and request totals for an endpoint
+    pub fn get_team_usage(&self, endpoint: &str) -> HashMap<String, serde_json::Value> {
+        let mut result = HashMap::new();
+
+        if let Some(teams) = self.team_usage.get(endpoint) {
+            for (team, usage) in teams.iter() {
+                result.insert(
+                    team.clone(),
+                    serde_json::json!({
+                        "tokens": usage.tokens,
+                        "requests": usage.requests
+                    }),
+                );
+            }
+        }
+
+        result
     }
 
     pub fn increment_active_requests(&self, endpoint: &str) {
@@ -177,6 +1221,10 @@ impl AdvancedRouter {
                 "active_requests".to_string(),
                 serde_json::Value::Number(serde_json::Number::from(metrics.active_requests)),
             );
+            endpoint_metrics.insert(
+                "latency_samples".to_string(),
+                serde_json::Value::Number(serde_json::Number::from(metrics.latency_samples)),
+            );
 
             result.insert(
                 endpoint.clone(),
@@ -184,6 +1232,846 @@ impl AdvancedRouter {
             );
         }
 
+        let now = now_ms();
+
+        // Surface circuit breaker state for every endpoint that has one
+        for entry in self.breakers.iter() {
+            let breaker = entry.value();
+            let endpoint_metrics = result
+                .entry(entry.key().clone())
+                .or_insert_with(|| serde_json::Value::Object(serde_json::Map::new()));
+            if let serde_json::Value::Object(obj) = endpoint_metrics {
+                obj.insert(
+                    "breaker_state".to_string(),
+                    serde_json::Value::String(breaker.state_name(now).to_string()),
+                );
+                obj.insert(
+                    "breaker_failure_rate".to_string(),
+                    serde_json::Number::from_f64(breaker.failure_rate())
+                        .map(serde_json::Value::Number)
+                        .unwrap_or_else(|| serde_json::Value::Number(serde_json::Number::from(0))),
+                );
+            }
+        }
+
+        // Surface active cooldowns, including endpoints with no metrics yet
+        for entry in self.cooldowns.iter() {
+            let cooldown = entry.value();
+            if cooldown.until_ms <= now {
+                continue;
+            }
+
+            let endpoint_metrics = result
+                .entry(entry.key().clone())
+                .or_insert_with(|| serde_json::Value::Object(serde_json::Map::new()));
+            if let serde_json::Value::Object(obj) = endpoint_metrics {
+                obj.insert("in_cooldown".to_string(), serde_json::Value::Bool(true));
+                obj.insert(
+                    "cooldown_remaining_ms".to_string(),
+                    serde_json::Value::Number(serde_json::Number::from(cooldown.until_ms - now)),
+                );
+                obj.insert(
+                    "cooldown_reason".to_string(),
+                    serde_json::Value::String(cooldown.reason.clone()),
+                );
+            }
+        }
+
         result
     }
 }
+
+// Global router
+lazy_static::lazy_static! {
+    static ref ADVANCED_ROUTER: AdvancedRouter = AdvancedRouter::new();
+}
+
+pub fn add_route(name: String, config: RouteConfig) {
+    ADVANCED_ROUTER.add_route(name, config);
+}
+
+pub fn route_excluding(route_name: &str, exclude: &[String]) -> Result<String, RoutingError> {
+    ADVANCED_ROUTER.route_excluding(route_name, exclude)
+}
+
+pub fn route_within_budget(
+    route_name: &str,
+    max_cost_usd: f64,
+    input_tokens: usize,
+    max_tokens: Option<usize>,
+) -> Result<String, RoutingError> {
+    ADVANCED_ROUTER.route_within_budget(route_name, max_cost_usd, input_tokens, max_tokens)
+}
+
+pub fn mark_endpoint_unhealthy(endpoint: &str, reason: Option<&str>) {
+    ADVANCED_ROUTER.mark_endpoint_unhealthy(endpoint, reason);
+}
+
+pub fn mark_endpoint_healthy(endpoint: &str) {
+    ADVANCED_ROUTER.mark_endpoint_healthy(endpoint);
+}
+
+pub fn update_metrics(endpoint: &str, latency: f64, success: bool, cost: f64) {
+    ADVANCED_ROUTER.update_metrics(endpoint, latency, success, cost);
+}
+
+pub fn increment_active_requests(endpoint: &str) {
+    ADVANCED_ROUTER.increment_active_requests(endpoint);
+}
+
+pub fn decrement_active_requests(endpoint: &str) {
+    ADVANCED_ROUTER.decrement_active_requests(endpoint);
+}
+
+pub fn get_metrics() -> HashMap<String, serde_json::Value> {
+    ADVANCED_ROUTER.get_metrics()
+}
+
+pub fn update_metrics_tagged(
+    endpoint: &str,
+    latency: f64,
+    success: bool,
+    cost: f64,
+    tokens: u64,
+    team: &str,
+) {
+    ADVANCED_ROUTER.update_metrics_tagged(endpoint, latency, success, cost, tokens, team);
+}
+
+pub fn get_team_usage(endpoint: &str) -> HashMap<String, serde_json::Value> {
+    ADVANCED_ROUTER.get_team_usage(endpoint)
+}
+
+pub fn record_attempt(endpoint: &str, success: bool) {
+    ADVANCED_ROUTER.record_attempt(endpoint, success);
+}
+
+pub fn get_adaptive_weights() -> HashMap<String, serde_json::Value> {
+    ADVANCED_ROUTER.get_adaptive_weights()
+}
+
+pub fn compare_strategies(
+    route_name: &str,
+) -> Result<HashMap<String, serde_json::Value>, RoutingError> {
+    ADVANCED_ROUTER.compare_strategies(route_name)
+}
+
+pub fn reset_stats(preserve_health: bool) {
+    ADVANCED_ROUTER.reset_stats(preserve_health);
+}
+
+pub fn rebalance_hint(restart_warmup: bool) {
+    ADVANCED_ROUTER.rebalance_hint(restart_warmup);
+}
+
+pub fn route_with_cost_ceiling(
+    route_name: &str,
+    max_output_cost_per_1m: f64,
+) -> Result<String, RoutingError> {
+    ADVANCED_ROUTER.route_with_cost_ceiling(route_name, max_output_cost_per_1m)
+}
+
+pub fn set_cost_ratio(model_family: &str, ratio: f64) {
+    ADVANCED_ROUTER.set_cost_ratio(model_family, ratio);
+}
+
+pub fn replace_routes(routes: HashMap<String, RouteConfig>) -> (usize, usize) {
+    ADVANCED_ROUTER.replace_routes(routes)
+}
+
+pub fn route_with_reason(
+    route_name: &str,
+    exclude: &[String],
+) -> Result<(String, &'static str), RoutingError> {
+    ADVANCED_ROUTER.route_with_reason(route_name, exclude)
+}
+
+pub fn get_warmup_factors() -> HashMap<String, serde_json::Value> {
+    ADVANCED_ROUTER.get_warmup_factors()
+}
+
+pub fn set_seed(seed: Option<u64>) {
+    ADVANCED_ROUTER.set_seed(seed);
+}
+
+pub fn snapshot() -> serde_json::Value {
+    ADVANCED_ROUTER.snapshot()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashSet;
+    use std::sync::Arc;
+
+    fn route(strategy: &str, endpoints: &[&str]) -> RouteConfig {
+        serde_json::from_value(serde_json::json!({
+            "strategy": strategy,
+            "endpoints": endpoints,
+            "weights": null,
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_replace_routes_is_atomic() {
+        let old: HashMap<String, RouteConfig> = [
+            (
+                "chat".to_string(),
+                route("simple_shuffle", &["old-1", "old-2"]),
+            ),
+            ("legacy".to_string(), route("simple_shuffle", &["old-3"])),
+        ]
+        .into();
+        let new: HashMap<String, RouteConfig> = [
+            (
+                "chat".to_string(),
+                route("simple_shuffle", &["new-1", "new-2"]),
+            ),
+            ("fresh".to_string(), route("simple_shuffle", &["new-3"])),
+        ]
+        .into();
+        let old_names: HashSet<String> = old.keys().cloned().collect();
+        let new_names: HashSet<String> = new.keys().cloned().collect();
+
+        let router = Arc::new(AdvancedRouter::new());
+        router.replace_routes(old.clone());
+
+        std::thread::scope(|scope| {
+            let writer = router.clone();
+            scope.spawn(move || {
+                for i in 0..500 {
+                    let set = if i % 2 == 0 { new.clone() } else { old.clone() };
+                    writer.replace_routes(set);
+                }
+            });
+
+            for _ in 0..2 {
+                let reader = router.clone();
+                let (old_names, new_names) = (old_names.clone(), new_names.clone());
+                scope.spawn(move || {
+                    for _ in 0..2000 {
+                        let snapshot = reader.snapshot();
+                        let names: HashSet<String> = snapshot["routes"]
+                            .as_object()
+                            .unwrap()
+                            .keys()
+                            .cloned()
+                            .collect();
+                        assert!(
+                            names == old_names || names == new_names,
+                            "mixed set {:?}",
+                            names
+                        );
+
+                        let endpoint = reader.route("chat").unwrap();
+                        assert!(["old-1", "old-2", "new-1", "new-2"].contains(&endpoint.as_str()));
+                    }
+                });
+            }
+        });
+    }
+
+    #[test]
+    fn test_cooldown_reasons_in_error_and_metrics() {
+        let router = AdvancedRouter::new();
+        router.add_route("chat".to_string(), route("simple_shuffle", &["a", "b"]));
+        router.mark_endpoint_unhealthy("a", Some("rate_limited"));
+        router.mark_endpoint_unhealthy("b", None);
+
+        let metrics = router.get_metrics();
+        assert_eq!(metrics["a"]["cooldown_reason"], "rate_limited");
+        assert_eq!(metrics["b"]["cooldown_reason"], "manual");
+        assert_eq!(metrics["a"]["in_cooldown"], true);
+
+        let err = router.route("chat").unwrap_err();
+        let RoutingError::NoHealthyEndpoints { cooldowns, .. } = &err else {
+            panic!("unexpected error {:?}", err);
+        };
+        let mut cooldowns = cooldowns.clone();
+        cooldowns.sort();
+        assert_eq!(
+            cooldowns,
+            vec![
+                ("a".to_string(), "rate_limited".to_string()),
+                ("b".to_string(), "manual".to_string()),
+            ]
+        );
+        assert!(err.to_string().contains("a=rate_limited"));
+
+        router.mark_endpoint_healthy("b");
+        assert_eq!(router.route("chat").unwrap(), "b");
+        assert!(!router.get_metrics().contains_key("b"));
+    }
+
+    #[test]
+    fn test_excluded_endpoints_reported_in_error() {
+        let router = AdvancedRouter::new();
+        router.add_route("chat".to_string(), route("simple_shuffle", &["a", "b"]));
+        router.mark_endpoint_unhealthy("a", Some("timeout"));
+
+        let err = router
+            .route_excluding("chat", &["b".to_string()])
+            .unwrap_err();
+        assert_eq!(
+            err,
+            RoutingError::NoHealthyEndpoints {
+                route: "chat".to_string(),
+                cooldowns: vec![
+                    ("a".to_string(), "timeout".to_string()),
+                    ("b".to_string(), "excluded".to_string()),
+                ],
+            }
+        );
+    }
+
+    #[test]
+    fn test_team_usage_attribution() {
+        let router = AdvancedRouter::new();
+        router.update_metrics_tagged("a", 100.0, true, 0.01, 120, "search");
+        router.update_metrics_tagged("a", 100.0, true, 0.01, 30, "search");
+        router.update_metrics_tagged("a", 100.0, false, 0.01, 7, "chat");
+
+        let usage = router.get_team_usage("a");
+        assert_eq!(
+            usage["search"],
+            serde_json::json!({"tokens": 150, "requests": 2})
+        );
+        assert_eq!(
+            usage["chat"],
+            serde_json::json!({"tokens": 7, "requests": 1})
+        );
+        assert!(router.get_team_usage("b").is_empty());
+        assert_eq!(router.get_metrics()["a"]["latency_samples"], 3);
+
+        router.reset_stats(true);
+        assert!(router.get_team_usage("a").is_empty());
+    }
+
+    #[test]
+    fn test_latency_routing_ignores_cold_endpoints() {
+        let router = AdvancedRouter::new();
+        router.add_route(
+            "chat".to_string(),
+            route("latency_based", &["cold", "slow", "warm"]),
+        );
+        for _ in 0..MIN_LATENCY_SAMPLES {
+            router.update_metrics("slow", 500.0, true, 0.0);
+            router.update_metrics("warm", 200.0, true, 0.0);
+        }
+        // One lucky sample does not make "cold" the fastest
+        router.update_metrics("cold", 1.0, true, 0.0);
+        assert_eq!(router.route("chat").unwrap(), "warm");
+
+        for _ in 1..MIN_LATENCY_SAMPLES {
+            router.update_metrics("cold", 1.0, true, 0.0);
+        }
+        assert_eq!(router.route("chat").unwrap(), "cold");
+    }
+
+    #[test]
+    fn test_latency_routing_spreads_over_cold_endpoints() {
+        let router = AdvancedRouter::new();
+        router.add_route("chat".to_string(), route("latency_based", &["a", "b", "c"]));
+        router.update_metrics("a", 100.0, true, 0.0);
+
+        let picked: std::collections::HashSet<String> =
+            (0..200).map(|_| router.route("chat").unwrap()).collect();
+        assert_eq!(picked.len(), 3, "picked {:?}", picked);
+    }
+
+    /// Pretend the endpoint's breaker opened `BREAKER_OPEN_MS` ago
+    fn expire_open_breaker(router: &AdvancedRouter, endpoint: &str) {
+        let mut breaker = router.breakers.get_mut(endpoint).unwrap();
+        assert!(matches!(breaker.state, BreakerState::Open { .. }));
+        breaker.state = BreakerState::Open {
+            since_ms: now_ms() - BREAKER_OPEN_MS,
+        };
+    }
+
+    #[test]
+    fn test_breaker_cycle_admits_one_probe() {
+        let router = AdvancedRouter::new();
+        router.add_route("chat".to_string(), route("simple_shuffle", &["a"]));
+        for _ in 0..BREAKER_MIN_ATTEMPTS {
+            router.record_attempt("a", false);
+        }
+        assert_eq!(router.get_metrics()["a"]["breaker_state"], "open");
+        assert_eq!(
+            router.route("chat").unwrap_err(),
+            RoutingError::NoHealthyEndpoints {
+                route: "chat".to_string(),
+                cooldowns: vec![("a".to_string(), "circuit_open".to_string())],
+            }
+        );
+
+        // Half-open: one probe goes through, everyone else waits on it
+        expire_open_breaker(&router, "a");
+        assert_eq!(router.get_metrics()["a"]["breaker_state"], "half_open");
+        assert_eq!(router.route("chat").unwrap(), "a");
+        assert!(router.route("chat").is_err());
+
+        // A failed probe reopens the breaker
+        router.record_attempt("a", false);
+        assert_eq!(router.get_metrics()["a"]["breaker_state"], "open");
+        assert!(router.route("chat").is_err());
+
+        // A successful probe closes it with a fresh window
+        expire_open_breaker(&router, "a");
+        assert_eq!(router.route("chat").unwrap(), "a");
+        router.record_attempt("a", true);
+        assert_eq!(router.get_metrics()["a"]["breaker_state"], "closed");
+        assert_eq!(router.get_metrics()["a"]["breaker_failure_rate"], 0.0);
+        assert_eq!(router.route("chat").unwrap(), "a");
+        assert_eq!(router.route("chat").unwrap(), "a");
+    }
+
+    #[test]
+    fn test_half_open_probe_is_exclusive_across_threads() {
+        let router = AdvancedRouter::new();
+        router.add_route("chat".to_string(), route("simple_shuffle", &["a", "b"]));
+        for _ in 0..BREAKER_MIN_ATTEMPTS {
+            router.record_attempt("a", false);
+        }
+        expire_open_breaker(&router, "a");
+
+        let barrier = std::sync::Barrier::new(8);
+        let picks: Vec<String> = std::thread::scope(|scope| {
+            let handles: Vec<_> = (0..8)
+                .map(|_| {
+                    scope.spawn(|| {
+                        barrier.wait();
+                        (0..50)
+                            .map(|_| router.route("chat").unwrap())
+                            .collect::<Vec<_>>()
+                    })
+                })
+                .collect();
+            handles
+                .into_iter()
+                .flat_map(|handle| handle.join().unwrap())
+                .collect()
+        });
+
+        assert_eq!(picks.iter().filter(|pick| *pick == "a").count(), 1);
+    }
+
+    #[test]
+    fn test_adaptive_weights_follow_latency() {
+        let router = AdvancedRouter::new();
+        let mut adaptive = route("weighted_shuffle", &["fast", "slow"]);
+        adaptive.adaptive_weights = true;
+        adaptive.min_weight = 0.9;
+        router.add_route("adaptive".to_string(), adaptive);
+        router.add_route(
+            "static".to_string(),
+            route("weighted_shuffle", &["fast", "slow"]),
+        );
+
+        for _ in 0..100 {
+            router.update_metrics("fast", 100.0, true, 0.0);
+            router.update_metrics("slow", 300.0, true, 0.0);
+        }
+
+        let weights = router.get_adaptive_weights();
+        assert_eq!(weights.len(), 1, "only adaptive routes are tuned");
+        let fast = weights["adaptive"]["fast"].as_f64().unwrap();
+        let slow = weights["adaptive"]["slow"].as_f64().unwrap();
+        // Targets are 2x and 2/3x the base weight; the slow one is floored
+        assert!((fast - 2.0).abs() < 0.05, "fast weight {}", fast);
+        assert!((0.9..0.91).contains(&slow), "slow weight {}", slow);
+    }
+
+    #[test]
+    fn test_single_healthy_endpoint_skips_strategy() {
+        let router = AdvancedRouter::new();
+        router.add_route("chat".to_string(), route("least_busy", &["a", "b"]));
+        assert_eq!(
+            router.route_with_reason("chat", &[]).unwrap(),
+            ("a".to_string(), "least-busy")
+        );
+
+        router.mark_endpoint_unhealthy("a", None);
+        assert_eq!(
+            router.route_with_reason("chat", &[]).unwrap(),
+            ("b".to_string(), "only-healthy")
+        );
+    }
+
+    #[test]
+    fn test_route_excluding_tries_each_endpoint_once() {
+        let router = AdvancedRouter::new();
+        router.add_route(
+            "chat".to_string(),
+            route("simple_shuffle", &["a", "b", "c"]),
+        );
+
+        let mut tried = Vec::new();
+        for _ in 0..3 {
+            let endpoint = router.route_excluding("chat", &tried).unwrap();
+            assert!(!tried.contains(&endpoint));
+            tried.push(endpoint);
+        }
+        tried.sort();
+        assert_eq!(tried, ["a", "b", "c"]);
+        assert!(matches!(
+            router.route_excluding("chat", &tried),
+            Err(RoutingError::NoHealthyEndpoints { .. })
+        ));
+    }
+
+    #[test]
+    fn test_compare_strategies() {
+        let router = AdvancedRouter::new();
+        router.add_route("chat".to_string(), route("simple_shuffle", &["a", "b"]));
+        for _ in 0..MIN_LATENCY_SAMPLES {
+            router.update_metrics("a", 100.0, true, 0.05);
+            router.update_metrics("b", 300.0, true, 0.01);
+        }
+        router.increment_active_requests("a");
+
+        let picks = router.compare_strategies("chat").unwrap();
+        assert_eq!(picks.len(), STRATEGIES.len());
+        assert_eq!(picks["least_busy"], "b");
+        assert_eq!(picks["latency_based"], "a");
+        assert_eq!(picks["cost_based"], "b");
+        assert!(picks["simple_shuffle"].is_string());
+        assert!(picks["weighted_shuffle"].is_string());
+        // Comparing doesn't touch load
+        assert_eq!(router.get_metrics()["a"]["active_requests"], 1);
+
+        router.mark_endpoint_unhealthy("a", None);
+        router.mark_endpoint_unhealthy("b", None);
+        let picks = router.compare_strategies("chat").unwrap();
+        assert!(picks.values().all(|pick| pick.is_null()));
+
+        assert_eq!(
+            router.compare_strategies("missing").unwrap_err(),
+            RoutingError::RouteNotFound("missing".to_string())
+        );
+    }
+
+    #[test]
+    fn test_reset_stats_keeps_warm_metrics() {
+        let router = AdvancedRouter::new();
+        router.add_route("chat".to_string(), route("latency_based", &["a"]));
+        router.update_metrics_tagged("a", 300.0, false, 0.02, 10, "search");
+        router.increment_active_requests("a");
+        router.mark_endpoint_unhealthy("a", Some("timeout"));
+        let before = router.get_metrics()["a"].clone();
+
+        router.reset_stats(true);
+        let metrics = &router.get_metrics()["a"];
+        for field in [
+            "latency_ms",
+            "success_rate",
+            "cost_per_request",
+            "latency_samples",
+        ] {
+            assert_eq!(metrics[field], before[field], "{}", field);
+        }
+        assert_eq!(metrics["latency_samples"], 1);
+        assert_eq!(metrics["active_requests"], 1);
+        assert_eq!(metrics["cooldown_reason"], "timeout");
+        assert!(router.get_team_usage("a").is_empty());
+
+        router.reset_stats(false);
+        assert!(router.get_metrics()["a"].get("in_cooldown").is_none());
+        router.decrement_active_requests("a");
+        assert_eq!(router.get_metrics()["a"]["active_requests"], 0);
+    }
+
+    /// Route whose endpoints serve GPT-4 and GPT-3.5 class test models
+    /// ($60 and $1.50 per 1M output tokens by default pricing)
+    fn priced_route(strategy: &str) -> RouteConfig {
+        let mut config = route(strategy, &["big", "small"]);
+        config.models = Some(vec![
+            "test-gpt-4-big".to_string(),
+            "test-gpt-3.5-small".to_string(),
+        ]);
+        config
+    }
+
+    #[test]
+    fn test_route_with_cost_ceiling() {
+        let router = AdvancedRouter::new();
+        router.add_route("chat".to_string(), priced_route("simple_shuffle"));
+
+        for _ in 0..10 {
+            assert_eq!(
+                router.route_with_cost_ceiling("chat", 10.0).unwrap(),
+                "small"
+            );
+        }
+        assert!(["big", "small"].contains(
+            &router
+                .route_with_cost_ceiling("chat", 60.0)
+                .unwrap()
+                .as_str()
+        ));
+        assert_eq!(
+            router.route_with_cost_ceiling("chat", 1.0).unwrap_err(),
+            RoutingError::CostCeilingExceeded {
+                route: "chat".to_string(),
+                ceiling: 1.0,
+                cheapest: "small".to_string(),
+                cheapest_cost: 1.5,
+            }
+        );
+
+        // Unhealthy endpoints are neither picked nor named as the cheapest
+        router.mark_endpoint_unhealthy("small", None);
+        assert!(matches!(
+            router.route_with_cost_ceiling("chat", 10.0),
+            Err(RoutingError::CostCeilingExceeded { cheapest, .. }) if cheapest == "big"
+        ));
+    }
+
+    #[test]
+    fn test_cost_ratio_changes_list_price_ranking() {
+        let router = AdvancedRouter::new();
+        let mut config = route("cost_based", &["gpt", "claude"]);
+        config.models = Some(vec![
+            "test-gpt-4-x".to_string(),
+            "test-claude-x".to_string(),
+        ]);
+        router.add_route("chat".to_string(), config);
+
+        // Default 3:1 input-heavy mix: $37.50 vs $30 blended
+        assert_eq!(router.route("chat").unwrap(), "claude");
+
+        // Output-heavy 1:2 mix: $50 vs $55 blended
+        router.set_cost_ratio("TEST-", 0.5);
+        assert_eq!(router.route("chat").unwrap(), "gpt");
+
+        // The longest matching family wins
+        router.set_cost_ratio("test-claude", 3.0);
+        assert_eq!(router.cost_ratio("test-claude-x"), 3.0);
+        assert_eq!(router.cost_ratio("test-gpt-4-x"), 0.5);
+        assert_eq!(router.cost_ratio("test-embedding-x"), f64::INFINITY);
+        assert_eq!(router.blended_price_per_1m("test-embedding-x"), 0.1);
+    }
+
+    #[test]
+    fn test_min_healthy_guard() {
+        let router = AdvancedRouter::new();
+        let mut config = route("simple_shuffle", &["a", "b", "c"]);
+        config.min_healthy = Some(2);
+        router.add_route("chat".to_string(), config);
+
+        router.mark_endpoint_unhealthy("a", None);
+        assert_eq!(
+            router.route_excluding("chat", &["b".to_string()]).unwrap(),
+            "c"
+        );
+
+        router.mark_endpoint_unhealthy("b", None);
+        let err = router.route("chat").unwrap_err();
+        assert_eq!(
+            err,
+            RoutingError::InsufficientHealthyEndpoints {
+                route: "chat".to_string(),
+                healthy: 1,
+                required: 2,
+            }
+        );
+        assert_eq!(
+            err.to_string(),
+            "Only 1 healthy endpoints for route 'chat', 2 required"
+        );
+    }
+
+    #[test]
+    fn test_reason_codes_per_strategy() {
+        let router = AdvancedRouter::new();
+        let expected = [
+            ("simple_shuffle", "random-pick"),
+            ("least_busy", "least-busy"),
+            ("latency_based", "lowest-latency"),
+            ("cost_based", "lowest-cost"),
+            ("weighted_shuffle", "weighted-pick"),
+            ("no_such_strategy", "random-pick"),
+        ];
+        for (strategy, reason) in expected {
+            router.add_route(strategy.to_string(), route(strategy, &["a", "b"]));
+            let (_, got) = router.route_with_reason(strategy, &[]).unwrap();
+            assert_eq!(got, reason, "strategy {}", strategy);
+        }
+    }
+
+    #[test]
+    fn test_warmup_factor_ramp() {
+        let router = AdvancedRouter::new();
+        let mut config = route("least_busy", &["new", "old"]);
+        config.warmup_seconds = Some(100);
+        router.add_route("chat".to_string(), config);
+        router.add_route("plain".to_string(), route("least_busy", &["new"]));
+        router.warming_since.insert("old".to_string(), 0);
+
+        let factor = |endpoint: &str| {
+            router.get_warmup_factors()["chat"][endpoint]
+                .as_f64()
+                .unwrap()
+        };
+        assert!((factor("new") - WARMUP_FLOOR).abs() < 0.01);
+        assert_eq!(factor("old"), 1.0);
+        assert!(!router.get_warmup_factors().contains_key("plain"));
+
+        // A warming endpoint looks busier: 1 / 0.1 against (2 + 1) / 1
+        router.update_metrics("old", 100.0, true, 0.0);
+        router.increment_active_requests("old");
+        router.increment_active_requests("old");
+        assert_eq!(router.route("chat").unwrap(), "old");
+
+        // Halfway through the ramp
+        router
+            .warming_since
+            .insert("new".to_string(), now_ms() - 50_000);
+        assert!((factor("new") - 0.55).abs() < 0.01);
+        assert_eq!(router.route("chat").unwrap(), "new");
+
+        // Recovering from a cooldown restarts the ramp
+        router.mark_endpoint_unhealthy("new", None);
+        router.mark_endpoint_healthy("new");
+        assert!((factor("new") - WARMUP_FLOOR).abs() < 0.01);
+    }
+
+    #[test]
+    fn test_seeded_routing_is_reproducible() {
+        let picks = |endpoints: &[&str], seed: u64| {
+            let router = AdvancedRouter::new();
+            router.add_route("shuffle".to_string(), route("simple_shuffle", endpoints));
+            router.add_route("weighted".to_string(), route("weighted_shuffle", endpoints));
+            router.set_seed(Some(seed));
+            (0..50)
+                .map(|i| {
+                    let name = if i % 2 == 0 { "shuffle" } else { "weighted" };
+                    router.route(name).unwrap()
+                })
+                .collect::<Vec<_>>()
+        };
+
+        let first = picks(&["a", "b", "c", "d"], 7);
+        assert_eq!(first, picks(&["a", "b", "c", "d"], 7));
+        // Config order doesn't matter, only the seed
+        assert_eq!(first, picks(&["d", "c", "b", "a"], 7));
+        assert_ne!(first, picks(&["a", "b", "c", "d"], 8));
+    }
+
+    #[test]
+    fn test_ties_break_by_endpoint_name() {
+        let router = AdvancedRouter::new();
+        router.add_route("busy".to_string(), route("least_busy", &["c", "b", "a"]));
+        router.add_route("cost".to_string(), route("cost_based", &["z-x", "y-x"]));
+        router.add_route("latency".to_string(), route("latency_based", &["q", "p"]));
+        for _ in 0..MIN_LATENCY_SAMPLES {
+            router.update_metrics("q", 100.0, true, 0.0);
+            router.update_metrics("p", 100.0, true, 0.0);
+        }
+
+        assert_eq!(router.route("busy").unwrap(), "a");
+        assert_eq!(router.route("cost").unwrap(), "y-x");
+        assert_eq!(router.route("latency").unwrap(), "p");
+    }
+
+    #[test]
+    fn test_max_tokens_changes_budget_routing() {
+        let router = AdvancedRouter::new();
+        router.add_route("chat".to_string(), priced_route("least_busy"));
+        router.update_metrics("small", 100.0, true, 0.0);
+        router.increment_active_requests("small");
+        router.increment_active_requests("small");
+
+        // A short reply fits the budget on either endpoint: least busy wins
+        assert_eq!(
+            router
+                .route_within_budget("chat", 0.01, 100, Some(50))
+                .unwrap(),
+            "big"
+        );
+        // A long one only fits on the cheap endpoint
+        assert_eq!(
+            router
+                .route_within_budget("chat", 0.01, 100, Some(1000))
+                .unwrap(),
+            "small"
+        );
+
+        let err = router
+            .route_within_budget("chat", 0.001, 100, Some(1000))
+            .unwrap_err();
+        let RoutingError::BudgetExceeded {
+            cheapest,
+            cheapest_cost,
+            ..
+        } = err
+        else {
+            panic!("unexpected error {:?}", err);
+        };
+        assert_eq!(cheapest, "small");
+        assert!((cheapest_cost - 0.00155).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_compare_strategies_leaves_seeded_sequence_alone() {
+        let picks = |compare: bool| {
+            let router = AdvancedRouter::new();
+            router.add_route(
+                "shuffle".to_string(),
+                route("simple_shuffle", &["a", "b", "c", "d"]),
+            );
+            router.add_route(
+                "weighted".to_string(),
+                route("weighted_shuffle", &["a", "b", "c", "d"]),
+            );
+            router.set_seed(Some(7));
+            (0..50)
+                .map(|i| {
+                    if compare {
+                        router.compare_strategies("weighted").unwrap();
+                    }
+                    let name = if i % 2 == 0 { "shuffle" } else { "weighted" };
+                    router.route(name).unwrap()
+                })
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(picks(false), picks(true));
+    }
+
+    #[test]
+    fn test_rebalance_hint_resets_only_volatile_state() {
+        let router = AdvancedRouter::new();
+        let mut adaptive = route("weighted_shuffle", &["fast", "slow"]);
+        adaptive.adaptive_weights = true;
+        adaptive.warmup_seconds = Some(100);
+        router.add_route("adaptive".to_string(), adaptive);
+        router.warming_since.insert("fast".to_string(), 0);
+        router.warming_since.insert("slow".to_string(), 0);
+        for _ in 0..20 {
+            router.update_metrics("fast", 100.0, true, 0.0);
+            router.update_metrics("slow", 300.0, true, 0.0);
+        }
+        router.mark_endpoint_unhealthy("slow", Some("timeout"));
+
+        router.rebalance_hint(false);
+        assert!(router.get_adaptive_weights().is_empty());
+        assert_eq!(router.get_warmup_factors()["adaptive"]["fast"], 1.0);
+        let metrics = router.get_metrics();
+        assert_eq!(metrics["fast"]["latency_samples"], 20);
+        assert_eq!(metrics["slow"]["cooldown_reason"], "timeout");
+
+        // Weights re-tune from the configured ones
+        router.update_metrics("fast", 100.0, true, 0.0);
+        let fast = router.get_adaptive_weights()["adaptive"]["fast"]
+            .as_f64()
+            .unwrap();
+        assert!(fast > 1.0 && fast < 1.5, "fast weight {}", fast);
+
+        router.rebalance_hint(true);
+        let factor = router.get_warmup_factors()["adaptive"]["fast"]
+            .as_f64()
+            .unwrap();
+        assert!((factor - WARMUP_FLOOR).abs() < 0.01);
+    }
+}
@@ -1,8 +1,17 @@
+use crate::hashing;
 use dashmap::DashMap;
 use serde::{Deserialize, Serialize};
 /// Feature flag management system
 use std::collections::HashMap;
-use std::sync::atomic::{AtomicBool, AtomicU32, Ordering};
+use std::sync::atomic::{AtomicBool, AtomicU32, AtomicU64, Ordering};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+fn now_ms() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_millis() as u64
+}
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum FeatureState {
@@ -20,6 +29,9 @@ pub struct FeatureFlag {
     pub error_count: AtomicU32,
     pub enabled: AtomicBool,
     pub error_threshold: u32,
+    /// Clear errors after this long without a new one (0 = manual reset only)
+    pub auto_reset_after_ms: AtomicU64,
+    last_error_ms: AtomicU64,
 }
 
 impl FeatureFlag {
@@ -31,10 +43,31 @@ impl FeatureFlag {
             error_count: AtomicU32::new(0),
             enabled: AtomicBool::new(enabled),
             error_threshold: 10,
+            auto_reset_after_ms: AtomicU64::new(0),
+            last_error_ms: AtomicU64::new(0),
+        }
+    }
+
+    /// Milliseconds until errors are cleared automatically, if an auto-reset
+    /// is configured and there are errors to clear
+    pub fn time_until_auto_reset(&self) -> Option<u64> {
+        let auto_reset_after_ms = self.auto_reset_after_ms.load(Ordering::Relaxed);
+        if auto_reset_after_ms == 0 || self.error_count.load(Ordering::Relaxed) == 0 {
+            return None;
+        }
+        let quiet_ms = now_ms().saturating_sub(self.last_error_ms.load(Ordering::Relaxed));
+        Some(auto_reset_after_ms.saturating_sub(quiet_ms))
+    }
+
+    /// Reset errors once the quiet period since the last error has elapsed
+    fn apply_auto_reset(&self) {
+        if self.time_until_auto_reset() == Some(0) {
+            self.reset_errors();
         }
     }
 
     pub fn is_enabled(&self, request_id: Option<&str>) -> bool {
+        self.apply_auto_reset();
         if !self.enabled.load(Ordering::Relaxed) {
             return false;
         }
@@ -45,16 +78,14 @@ impl FeatureFlag {
             FeatureState::Canary => {
                 // Simple hash-based canary deployment
                 if let Some(id) = request_id {
-                    let hash = self.simple_hash(id);
-                    hash % 100 < 5 // 5% canary
+                    hashing::hash_bucket(id, 100) < 5 // 5% canary
                 } else {
                     false
                 }
             }
             FeatureState::GradualRollout { percentage } => {
                 if let Some(id) = request_id {
-                    let hash = self.simple_hash(id);
-                    hash % 100 < (*percentage as u64)
+                    hashing::hash_bucket(id, 100) < (*percentage as u64)
                 } else {
                     false
                 }
@@ -64,6 +95,7 @@ impl FeatureFlag {
     }
 
     pub fn record_error(&self) {
+        self.last_error_ms.store(now_ms(), Ordering::Relaxed);
         let current_errors = self.error_count.fetch_add(1, Ordering::Relaxed);
         if current_errors >= self.error_threshold {
             self.enabled.store(false, Ordering::Relaxed);
@@ -74,14 +106,6 @@ impl FeatureFlag {
         self.error_count.store(0, Ordering::Relaxed);
         self.enabled.store(true, Ordering::Relaxed);
     }
-
-    fn simple_hash(&self, input: &str) -> u64 {
-        let mut hash = 5381u64;
-        for byte in input.bytes() {
-            hash = hash.wrapping_mul(33).wrapping_add(byte as u64);
-        }
-        hash
-    }
 }
 
 pub struct FeatureFlagManager {
@@ -111,6 +135,7 @@ impl FeatureFlagManager {
             FeatureState::GradualRollout { percentage: 25 },
         );
         manager.add_flag("rust_connection_pool".to_string(), FeatureState::Canary);
+        manager.add_flag("connection_pool_timing".to_string(), FeatureState::Enabled);
 
         manager
     }
@@ -134,6 +159,38 @@ impl FeatureFlagManager {
         }
     }
 
+    /// Clear a flag's errors automatically after `seconds` without a new
+    /// error; `None` restores manual-only reset. Returns false for unknown flags.
+    pub fn set_auto_reset(&self, feature_name: &str, seconds: Option<u64>) -> bool {
+        match self.flags.get(feature_name) {
+            Some(flag) => {
+                let ms = seconds.map(|s| s.saturating_mul(1000)).unwrap_or(0);
+                flag.auto_reset_after_ms.store(ms, Ordering::Relaxed);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Switch a flag fully on or off, keeping its error count and auto-reset
+    /// setting. Returns false for unknown flags.
+    pub fn set_enabled(&self, feature_name: &str, enabled: bool) -> bool {
+        match self.flags.get_mut(feature_name) {
+            Some(mut flag) => {
+                flag.state = if enabled {
+                    FeatureState::Enabled
+                } else {
+                    FeatureState::Disabled
+                };
+                // A flag tripped by errors stays off until they're reset
+                let tripped = flag.error_count.load(Ordering::Relaxed) > flag.error_threshold;
+                flag.enabled.store(enabled && !tripped, Ordering::Relaxed);
+                true
+            }
+            None => false,
+        }
+    }
+
     pub fn reset_errors(&self, feature_name: Option<&str>) {
         if let Some(name) = feature_name {
             if let Some(flag) = self.flags.get(name) {
@@ -153,6 +210,7 @@ impl FeatureFlagManager {
         for entry in self.flags.iter() {
             let name = entry.key();
             let flag = entry.value();
+            flag.apply_auto_reset();
 
             let mut flag_status = HashMap::new();
             flag_status.insert(
@@ -184,6 +242,23 @@ impl FeatureFlagManager {
                 serde_json::Value::Number(serde_json::Number::from(flag.error_threshold)),
             );
 
+            let auto_reset_after_ms = flag.auto_reset_after_ms.load(Ordering::Relaxed);
+            flag_status.insert(
+                "auto_reset_after_seconds".to_string(),
+                if auto_reset_after_ms > 0 {
+                    serde_json::json!(auto_reset_after_ms / 1000)
+                } else {
+                    serde_json::Value::Null
+                },
+            );
+            flag_status.insert(
+                "seconds_until_auto_reset".to_string(),
+                match flag.time_until_auto_reset() {
+                    Some(ms) => serde_json::json!(ms as f64 / 1000.0),
+                    None => serde_json::Value::Null,
+                },
+            );
+
             result.insert(
                 name.clone(),
                 serde_json::Value::Object(flag_status.into_iter().collect()),
@@ -211,6 +286,93 @@ pub fn reset_feature_errors(feature_name: Option<&str>) {
     FEATURE_MANAGER.reset_errors(feature_name);
 }
 
+pub fn set_feature_auto_reset(feature_name: &str, seconds: Option<u64>) -> bool {
+    FEATURE_MANAGER.set_auto_reset(feature_name, seconds)
+}
+
+pub fn set_feature_enabled(feature_name: &str, enabled: bool) -> bool {
+    FEATURE_MANAGER.set_enabled(feature_name, enabled)
+}
+
 pub fn get_all_feature_status() -> HashMap<String, serde_json::Value> {
     FEATURE_MANAGER.get_status()
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn trip(manager: &FeatureFlagManager, name: &str) {
+        for _ in 0..=manager.flags.get(name).unwrap().error_threshold {
+            manager.record_error(name);
+        }
+        assert!(!manager.is_enabled(name, None));
+    }
+
+    fn backdate_last_error(manager: &FeatureFlagManager, name: &str, ms: u64) {
+        let flag = manager.flags.get(name).unwrap();
+        flag.last_error_ms.fetch_sub(ms, Ordering::Relaxed);
+    }
+
+    #[test]
+    fn test_auto_reset_after_quiet_period() {
+        let manager = FeatureFlagManager::new();
+        manager.add_flag("auto".to_string(), FeatureState::Enabled);
+        assert!(manager.set_auto_reset("auto", Some(60)));
+        assert!(!manager.set_auto_reset("missing", Some(60)));
+        trip(&manager, "auto");
+
+        let status = &manager.get_status()["auto"];
+        assert_eq!(status["auto_reset_after_seconds"], 60);
+        let remaining = status["seconds_until_auto_reset"].as_f64().unwrap();
+        assert!(remaining > 59.0 && remaining <= 60.0, "{}", remaining);
+
+        backdate_last_error(&manager, "auto", 30_000);
+        assert!(!manager.is_enabled("auto", None));
+        backdate_last_error(&manager, "auto", 30_000);
+        assert!(manager.is_enabled("auto", None));
+        let status = &manager.get_status()["auto"];
+        assert_eq!(status["error_count"], 0);
+        assert!(status["seconds_until_auto_reset"].is_null());
+    }
+
+    #[test]
+    fn test_without_auto_reset_errors_stay_until_manual_reset() {
+        let manager = FeatureFlagManager::new();
+        manager.add_flag("manual".to_string(), FeatureState::Enabled);
+        trip(&manager, "manual");
+
+        backdate_last_error(&manager, "manual", 3_600_000);
+        assert!(!manager.is_enabled("manual", None));
+        assert!(manager.get_status()["manual"]["auto_reset_after_seconds"].is_null());
+
+        // Turning auto-reset off again restores manual-only behaviour
+        manager.set_auto_reset("manual", Some(1));
+        manager.set_auto_reset("manual", None);
+        assert!(!manager.is_enabled("manual", None));
+
+        manager.reset_errors(Some("manual"));
+        assert!(manager.is_enabled("manual", None));
+    }
+
+    #[test]
+    fn test_set_enabled_keeps_error_state() {
+        let manager = FeatureFlagManager::new();
+        assert!(manager.is_enabled("connection_pool_timing", None));
+        assert!(manager.set_enabled("connection_pool_timing", false));
+        assert!(!manager.is_enabled("connection_pool_timing", None));
+        assert!(manager.set_enabled("connection_pool_timing", true));
+        assert!(manager.is_enabled("connection_pool_timing", None));
+        assert!(!manager.set_enabled("missing", true));
+
+        // A tripped flag stays off until its errors are reset
+        trip(&manager, "connection_pool_timing");
+        assert!(manager.set_enabled("connection_pool_timing", true));
+        assert!(!manager.is_enabled("connection_pool_timing", None));
+        manager.reset_errors(Some("connection_pool_timing"));
+        assert!(manager.is_enabled("connection_pool_timing", None));
+
+        assert!(manager.set_enabled("connection_pool_timing", false));
+        assert!(!manager.is_enabled("connection_pool_timing", None));
+    }
+}
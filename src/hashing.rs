@@ -0,0 +1,42 @@
+/// Stable, non-cryptographic string hashing shared by rollouts and routing
+///
+/// Uses 64-bit FNV-1a. The output for a given key is fixed by the algorithm
+/// and constants below, so it is stable across runs, processes and releases
+/// and can be reproduced from Python via `hash_bucket`.
+const FNV_OFFSET_BASIS: u64 = 0xcbf2_9ce4_8422_2325;
+const FNV_PRIME: u64 = 0x0000_0100_0000_01b3;
+
+/// Hash a string to a stable 64-bit value
+pub fn stable_hash(key: &str) -> u64 {
+    key.bytes().fold(FNV_OFFSET_BASIS, |hash, byte| {
+        (hash ^ byte as u64).wrapping_mul(FNV_PRIME)
+    })
+}
+
+/// Map a string to a bucket in `[0, buckets)`
+///
+/// `buckets` must be non-zero.
+pub fn hash_bucket(key: &str, buckets: u64) -> u64 {
+    stable_hash(key) % buckets
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_stable_hash_known_values() {
+        // Reference FNV-1a 64-bit values; these must never change
+        assert_eq!(stable_hash(""), 0xcbf2_9ce4_8422_2325);
+        assert_eq!(stable_hash("a"), 0xaf63_dc4c_8601_ec8c);
+        assert_eq!(stable_hash("foobar"), 0x8594_4171_f739_67e8);
+    }
+
+    #[test]
+    fn test_hash_bucket_range() {
+        for i in 0..1000 {
+            assert!(hash_bucket(&format!("request-{}", i), 7) < 7);
+        }
+        assert_eq!(hash_bucket("anything", 1), 0);
+    }
+}
@@ -5,9 +5,14 @@
 //! connection pooling.
 
 use pyo3::prelude::*;
-use pyo3::types::{PyDict, PyList};
-use rand::Rng;
+use pyo3::types::{PyBytes, PyDict, PyList, PyString};
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
 use std::collections::HashMap;
+use std::sync::Mutex;
+
+/// Seeded generator for deployment picks; None uses the thread RNG
+static ROUTING_RNG: Mutex<Option<StdRng>> = Mutex::new(None);
 
 /// Generate a random index in [0, len) without modulo bias
 ///
@@ -17,7 +22,17 @@ fn random_index(len: usize) -> usize {
     if len == 0 {
         return 0;
     }
-    let mut rng = rand::thread_rng();
+    match ROUTING_RNG
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .as_mut()
+    {
+        Some(rng) => unbiased_index(rng, len),
+        None => unbiased_index(&mut rand::thread_rng(), len),
+    }
+}
+
+fn unbiased_index(rng: &mut impl Rng, len: usize) -> usize {
     // Use full usize range, not just 32 bits
     let limit = usize::MAX / len * len;
     loop {
@@ -28,6 +43,163 @@ fn random_index(len: usize) -> usize {
     }
 }
 
+// Helper function to extract OpenAI-style chat messages from a Python list of dicts
+fn extract_chat_messages(messages: &Bound<'_, PyAny>) -> PyResult<Vec<tokens::ChatMessage>> {
+    let mut result = Vec::new();
+
+    for item in messages.try_iter()? {
+        let item = item?;
+        let dict = item.downcast::<PyDict>()?;
+        let mut message = tokens::ChatMessage::default();
+
+        if let Some(role) = dict.get_item("role")? {
+            message.role = role.extract()?;
+        }
+        if let Some(name) = dict.get_item("name")? {
+            if !name.is_none() {
+                message.name = Some(name.extract()?);
+            }
+        }
+        if let Some(content) = dict.get_item("content")? {
+            if let Ok(text) = content.extract::<String>() {
+                message.content = text;
+            } else if let Ok(parts) = content.downcast::<PyList>() {
+                // Multimodal content: only text parts count as text tokens
+                let mut texts = Vec::new();
+                for part in parts.iter() {
+                    if let Ok(part) = part.downcast::<PyDict>() {
+                        if let Some(text) = part.get_item("text")? {
+                            texts.push(text.extract::<String>()?);
+                        }
+                    }
+                }
+                message.content = texts.join("\n");
+            }
+        }
+
+        result.push(message);
+    }
+
+    Ok(result)
+}
+
+// Helper function to read a field from a dict or an object attribute, treating None as absent
+fn get_usage_field<'py>(
+    usage: &Bound<'py, PyAny>,
+    field: &str,
+) -> PyResult<Option<Bound<'py, PyAny>>> {
+    let value = if let Ok(dict) = usage.downcast::<PyDict>() {
+        dict.get_item(field)?
+    } else if usage.hasattr(field)? {
+        Some(usage.getattr(field)?)
+    } else {
+        None
+    };
+    Ok(value.filter(|value| !value.is_none()))
+}
+
+// Helper function to read a token count from an OpenAI-style usage payload (0 if absent)
+fn get_usage_count(usage: &Bound<'_, PyAny>, field: &str) -> PyResult<usize> {
+    get_usage_field(usage, field)?
+        .map(|value| value.extract())
+        .transpose()
+        .map(|count| count.unwrap_or(0))
+}
+
+// Helper function to check a Python value is a str equal to one of `names`
+fn str_in(value: &Bound<'_, PyAny>, names: &[&str]) -> bool {
+    value
+        .downcast::<PyString>()
+        .is_ok_and(|value| value.to_cow().is_ok_and(|value| names.contains(&&*value)))
+}
+
+// Helper function to check a deployment's `model_info` against the filters:
+// its `id` must not be in `exclude_ids` and it must have every required
+// key/value. `model_info` is read once, and only if a filter is set.
+fn model_info_admits(
+    deployment: &Bound<'_, PyDict>,
+    exclude_ids: &[&str],
+    required: &[(Bound<'_, PyAny>, Bound<'_, PyAny>)],
+) -> PyResult<bool> {
+    if exclude_ids.is_empty() && required.is_empty() {
+        return Ok(true);
+    }
+    let model_info = deployment.get_item("model_info")?;
+    let Some(model_info) = model_info
+        .as_ref()
+        .and_then(|info| info.downcast::<PyDict>().ok())
+    else {
+        return Ok(required.is_empty());
+    };
+
+    if !exclude_ids.is_empty() {
+        if let Some(id) = model_info.get_item("id")? {
+            if str_in(&id, exclude_ids) {
+                return Ok(false);
+            }
+        }
+    }
+    for (key, value) in required {
+        match model_info.get_item(key)? {
+            Some(actual) if actual.eq(value)? => {}
+            _ => return Ok(false),
+        }
+    }
+    Ok(true)
+}
+
+// Helper function to pick a random deployment of `model` from a model list,
+// shared by `get_available_deployment` and `AdvancedRouter.get_available_deployment`
+fn pick_deployment<'py>(
+    model_list: &[Bound<'py, PyAny>],
+    model: &str,
+    blocked_models: Option<Vec<String>>,
+    exclude_ids: Option<Vec<String>>,
+    required_model_info: Option<Bound<'py, PyDict>>,
+) -> PyResult<Option<Bound<'py, PyAny>>> {
+    if blocked_models.is_some_and(|blocked| blocked.iter().any(|name| name == model)) {
+        return Ok(None);
+    }
+    let exclude_ids = exclude_ids.unwrap_or_default();
+    let exclude_ids: Vec<&str> = exclude_ids.iter().map(String::as_str).collect();
+    let required: Vec<(Bound<'py, PyAny>, Bound<'py, PyAny>)> = required_model_info
+        .map(|required| required.iter().collect())
+        .unwrap_or_default();
+
+    let mut available = Vec::new();
+    for item in model_list {
+        if let Ok(dict) = item.downcast::<PyDict>() {
+            if let Ok(Some(name)) = dict.get_item("model_name") {
+                if str_in(&name, &[model]) && model_info_admits(dict, &exclude_ids, &required)? {
+                    available.push(item);
+                }
+            }
+        }
+    }
+
+    if available.is_empty() {
+        // No matching model found - return None instead of silently using wrong model
+        return Ok(None);
+    }
+    let index = random_index(available.len());
+    Ok(Some(available[index].clone()))
+}
+
+// Helper function to convert a RateLimitResult to a PyDict
+fn rate_limit_result_to_pydict(
+    py: Python,
+    result: rate_limiter::RateLimitResult,
+) -> PyResult<PyObject> {
+    let dict = PyDict::new(py);
+    dict.set_item("allowed", result.allowed)?;
+    dict.set_item("reason", result.reason)?;
+    dict.set_item("remaining_requests", result.remaining_requests)?;
+    if let Some(retry_after) = result.retry_after_ms {
+        dict.set_item("retry_after_ms", retry_after)?;
+    }
+    Ok(dict.into())
+}
+
 // Helper function to convert HashMap<String, serde_json::Value> to PyDict
 fn convert_hashmap_to_pydict(
     py: Python,
@@ -81,6 +253,7 @@ fn convert_json_value_to_py(py: Python, value: serde_json::Value) -> PyResult<Py
 pub mod connection_pool;
 pub mod core;
 pub mod feature_flags;
+pub mod hashing;
 pub mod performance_monitor;
 pub mod pricing;
 pub mod rate_limiter;
@@ -114,6 +287,52 @@ impl SimpleTokenCounter {
         tokens::count_tokens(text, model).map_err(pyo3::exceptions::PyValueError::new_err)
     }
 
+    /// Approximate token count (±10%) without running the encoder
+    #[pyo3(signature = (text, model=None))]
+    fn count_tokens_approx(&self, text: &str, model: Option<&str>) -> usize {
+        tokens::count_tokens_approx(text, model)
+    }
+
+    /// Name of the encoding used to count tokens for a model (e.g. "o200k_base")
+    fn encoding_for_model(&self, model: &str) -> &'static str {
+        tokens::encoding_for_model(model)
+    }
+
+    /// Raw bytes of a single token id (bytes, since a token may be partial UTF-8)
+    fn decode_single<'py>(
+        &self,
+        py: Python<'py>,
+        token_id: u32,
+        model: &str,
+    ) -> PyResult<Bound<'py, PyBytes>> {
+        let bytes = tokens::decode_single(token_id, model)
+            .map_err(pyo3::exceptions::PyValueError::new_err)?;
+        Ok(PyBytes::new(py, &bytes))
+    }
+
+    /// Count a fixed prompt prefix once for reuse with `count_with_prefix`
+    fn count_prefix(&self, prefix: &str, model: &str) -> PyResult<usize> {
+        tokens::count_prefix(prefix, model).map_err(pyo3::exceptions::PyValueError::new_err)
+    }
+
+    /// Count `prefix + suffix` encoding only the suffix
+    ///
+    /// Exact when the prefix ends on a token boundary (e.g. a separator).
+    fn count_with_prefix(
+        &self,
+        prefix_token_count: usize,
+        suffix: &str,
+        model: &str,
+    ) -> PyResult<usize> {
+        tokens::count_with_prefix(prefix_token_count, suffix, model)
+            .map_err(pyo3::exceptions::PyValueError::new_err)
+    }
+
+    /// Number of leading tokens shared by two prompts (cache-eligible prefix)
+    fn common_prefix_tokens(&self, a: &str, b: &str, model: &str) -> PyResult<usize> {
+        tokens::common_prefix_tokens(a, b, model).map_err(pyo3::exceptions::PyValueError::new_err)
+    }
+
     /// Count tokens for multiple texts at once
     #[pyo3(signature = (texts, model=None))]
     fn count_tokens_batch(&self, texts: Vec<String>, model: Option<&str>) -> PyResult<Vec<usize>> {
@@ -142,6 +361,93 @@ impl SimpleTokenCounter {
         tokens::validate_input(text, model).map_err(pyo3::exceptions::PyValueError::new_err)
     }
 
+    /// Load encodings for the given models concurrently (GIL released)
+    ///
+    /// Returns per-model success, encoding and load time.
+    fn preload_models_parallel(&self, py: Python, models: Vec<String>) -> PyResult<PyObject> {
+        let result = py.allow_threads(|| tokens::preload_models_parallel(&models));
+        convert_hashmap_to_pydict(py, result)
+    }
+
+    /// Measure encode throughput (tokens/sec) with the GIL released
+    #[pyo3(signature = (sample_text, model, iterations=1000))]
+    fn benchmark_encode(
+        &self,
+        py: Python,
+        sample_text: &str,
+        model: &str,
+        iterations: usize,
+    ) -> PyResult<PyObject> {
+        let result = py
+            .allow_threads(|| tokens::benchmark_encode(sample_text, model, iterations))
+            .map_err(pyo3::exceptions::PyValueError::new_err)?;
+        convert_hashmap_to_pydict(py, result)
+    }
+
+    /// Count completion tokens for response text (no chat message overhead)
+    fn count_completion_tokens(&self, text: &str, model: &str) -> PyResult<usize> {
+        tokens::count_completion_tokens(text, model)
+            .map_err(pyo3::exceptions::PyValueError::new_err)
+    }
+
+    /// Check token counts against known-good `(model, text, expected)` cases
+    ///
+    /// Returns `{passed, failed, results}`; each result has the model, its
+    /// encoding, and the expected and actual counts.
+    fn verify_counts(&self, py: Python, cases: Vec<(String, String, usize)>) -> PyResult<PyObject> {
+        let summary = py.allow_threads(|| tokens::verify_counts(&cases));
+        convert_hashmap_to_pydict(py, summary)
+    }
+
+    /// Count tokens and their cost in one call; returns `{tokens, cost_usd}`
+    ///
+    /// `is_output` selects the output rather than the input token price.
+    #[pyo3(signature = (text, model, is_output=false))]
+    fn count_and_cost(
+        &self,
+        py: Python,
+        text: &str,
+        model: &str,
+        is_output: bool,
+    ) -> PyResult<PyObject> {
+        let (token_count, cost_usd) = tokens::count_and_cost(text, model, is_output)
+            .map_err(pyo3::exceptions::PyValueError::new_err)?;
+        let dict = PyDict::new(py);
+        dict.set_item("tokens", token_count)?;
+        dict.set_item("cost_usd", cost_usd)?;
+        Ok(dict.into())
+    }
+
+    /// Count prompt tokens for a list of chat messages (with message overhead)
+    ///
+    /// `image_tokens` is added as-is, for image costs already known from the
+    /// provider; image parts themselves are not counted.
+    #[pyo3(signature = (messages, model=None, image_tokens=0))]
+    fn count_message_tokens(
+        &self,
+        messages: &Bound<'_, PyAny>,
+        model: Option<&str>,
+        image_tokens: usize,
+    ) -> PyResult<usize> {
+        let messages = extract_chat_messages(messages)?;
+        tokens::count_message_tokens_with_images(&messages, model, image_tokens)
+            .map_err(pyo3::exceptions::PyValueError::new_err)
+    }
+
+    /// Tokens left before the context window, after reserving `reserve_output`
+    /// for the response (negative if already over)
+    #[pyo3(signature = (messages, model, reserve_output=0))]
+    fn remaining_budget(
+        &self,
+        messages: &Bound<'_, PyAny>,
+        model: &str,
+        reserve_output: usize,
+    ) -> PyResult<i64> {
+        let messages = extract_chat_messages(messages)?;
+        tokens::remaining_budget(&messages, model, reserve_output)
+            .map_err(pyo3::exceptions::PyValueError::new_err)
+    }
+
     #[getter]
     fn model_max_tokens(&self) -> usize {
         self.model_max_tokens
@@ -157,8 +463,8 @@ pub struct SimpleRateLimiter {
 #[pymethods]
 impl SimpleRateLimiter {
     #[new]
-    #[pyo3(signature = (requests_per_minute=60))]
-    fn new(requests_per_minute: u64) -> Self {
+    #[pyo3(signature = (requests_per_minute=60, max_keys=None))]
+    fn new(requests_per_minute: u64, max_keys: Option<usize>) -> Self {
         // Configure default rate limit with proper calculations
         let requests_per_second = (requests_per_minute as f64 / 60.0).ceil() as u64;
         let burst_size = ((requests_per_minute as f64 / 10.0).ceil() as u64).max(5);
@@ -170,32 +476,142 @@ impl SimpleRateLimiter {
             burst_size,
         };
         rate_limiter::set_rate_limit_config("default", config);
+        if let Some(max_keys) = max_keys {
+            rate_limiter::set_max_keys(max_keys);
+        }
         Self {
             default_key: "default".to_string(),
         }
     }
 
     /// Check if a request is allowed
-    #[pyo3(signature = (key=None))]
-    fn check(&self, py: Python, key: Option<&str>) -> PyResult<PyObject> {
+    ///
+    /// When `max_wait_ms` is given, blocks (without holding the GIL) up to that
+    /// long for a slot to free up instead of rejecting immediately. `cost`
+    /// weights the request against the limits (default 1).
+    #[pyo3(signature = (key=None, max_wait_ms=None, cost=None))]
+    fn check(
+        &self,
+        py: Python,
+        key: Option<&str>,
+        max_wait_ms: Option<u64>,
+        cost: Option<u64>,
+    ) -> PyResult<PyObject> {
         let key = key.unwrap_or(&self.default_key);
-        let result = rate_limiter::check_rate_limit(key);
+        let cost = cost.unwrap_or(1);
+        let result = match max_wait_ms {
+            Some(max_wait_ms) => {
+                py.allow_threads(|| rate_limiter::check_rate_limit_blocking(key, max_wait_ms, cost))
+            }
+            None => rate_limiter::check_rate_limit_with_cost(key, cost),
+        };
 
-        let dict = PyDict::new(py);
-        dict.set_item("allowed", result.allowed)?;
-        dict.set_item("reason", result.reason)?;
-        dict.set_item("remaining_requests", result.remaining_requests)?;
-        if let Some(retry_after) = result.retry_after_ms {
-            dict.set_item("retry_after_ms", retry_after)?;
-        }
-        Ok(dict.into())
+        rate_limit_result_to_pydict(py, result)
+    }
+
+    /// Check a request and return an `AdmissionResult` describing the outcome
+    #[pyo3(signature = (key=None, max_wait_ms=None, cost=None))]
+    fn check_detailed(
+        &self,
+        py: Python,
+        key: Option<&str>,
+        max_wait_ms: Option<u64>,
+        cost: Option<u64>,
+    ) -> AdmissionResult {
+        let key = key.unwrap_or(&self.default_key);
+        let cost = cost.unwrap_or(1);
+        let result = match max_wait_ms {
+            Some(max_wait_ms) => {
+                py.allow_threads(|| rate_limiter::check_rate_limit_blocking(key, max_wait_ms, cost))
+            }
+            None => rate_limiter::check_rate_limit_with_cost(key, cost),
+        };
+
+        AdmissionResult::from(result)
+    }
+
+    /// Check `limit` per `window_seconds` for a key and report its usage level
+    ///
+    /// Adds `current_count` and `level` ("ok"/"warning"/"critical") to the
+    /// usual result dict.
+    #[pyo3(signature = (key, limit, window_seconds=60))]
+    fn check_rate_limit_with_level(
+        &self,
+        py: Python,
+        key: &str,
+        limit: u64,
+        window_seconds: u64,
+    ) -> PyResult<PyObject> {
+        let (result, count, level) =
+            rate_limiter::check_rate_limit_with_level(key, limit, window_seconds);
+        let dict = rate_limit_result_to_pydict(py, result)?;
+        let dict = dict.downcast_bound::<PyDict>(py)?;
+        dict.set_item("current_count", count)?;
+        dict.set_item("level", level)?;
+        Ok(dict.clone().into())
+    }
+
+    /// Set the usage fractions for the "warning" and "critical" levels
+    fn set_level_thresholds(&self, warning: f64, critical: f64) {
+        rate_limiter::set_level_thresholds(warning, critical);
+    }
+
+    /// Cap estimated spend (USD) per key over a sliding window
+    ///
+    /// Rejections come back with kind "over_budget", or "unpriced_model" when
+    /// the model has no pricing data.
+    #[pyo3(signature = (key, model, est_input_tokens, est_output_tokens, limit_usd, window_seconds=60))]
+    fn check_cost_limit(
+        &self,
+        key: &str,
+        model: &str,
+        est_input_tokens: usize,
+        est_output_tokens: usize,
+        limit_usd: f64,
+        window_seconds: u64,
+    ) -> AdmissionResult {
+        rate_limiter::check_cost_limit(
+            key,
+            model,
+            est_input_tokens,
+            est_output_tokens,
+            limit_usd,
+            window_seconds,
+        )
+        .into()
     }
 
     /// Check rate limit and return boolean (simpler interface)
-    #[pyo3(signature = (key=None))]
-    fn is_allowed(&self, key: Option<&str>) -> bool {
+    #[pyo3(signature = (key=None, max_wait_ms=None, cost=None))]
+    fn is_allowed(
+        &self,
+        py: Python,
+        key: Option<&str>,
+        max_wait_ms: Option<u64>,
+        cost: Option<u64>,
+    ) -> bool {
         let key = key.unwrap_or(&self.default_key);
-        rate_limiter::check_rate_limit(key).allowed
+        let cost = cost.unwrap_or(1);
+        match max_wait_ms {
+            Some(max_wait_ms) => {
+                py.allow_threads(|| rate_limiter::check_rate_limit_blocking(key, max_wait_ms, cost))
+                    .allowed
+            }
+            None => rate_limiter::check_rate_limit_with_cost(key, cost).allowed,
+        }
+    }
+
+    /// Backfill a key's windows from a log replay; entries are (age_seconds_ago, requests)
+    fn consume_batch(&self, key: &str, entries: Vec<(f64, u64)>) {
+        rate_limiter::consume_batch(key, &entries);
+    }
+
+    /// Check a hierarchy of limits at once; each level is (key, limit, window_seconds)
+    ///
+    /// Admitted only if every level passes, recorded in all levels or none.
+    fn check_group(&self, py: Python, levels: Vec<(String, u64, u64)>) -> PyResult<PyObject> {
+        let result = rate_limiter::check_rate_limit_group(&levels);
+        rate_limit_result_to_pydict(py, result)
     }
 
     /// Get remaining requests for a key
@@ -210,6 +626,62 @@ impl SimpleRateLimiter {
         let stats = rate_limiter::get_rate_limit_stats();
         convert_hashmap_to_pydict(py, stats)
     }
+
+    /// Get one key's requests and tokens in the current window and when it
+    /// resets; None if the key isn't tracked
+    fn get_key_stats(&self, py: Python, key: &str) -> PyResult<Option<PyObject>> {
+        rate_limiter::get_key_stats(key)
+            .map(|stats| convert_hashmap_to_pydict(py, stats))
+            .transpose()
+    }
+
+    /// Get limiter-wide summary (tracked keys, key cap, evictions)
+    fn get_summary(&self, py: Python) -> PyResult<PyObject> {
+        let summary = rate_limiter::get_rate_limit_summary();
+        convert_hashmap_to_pydict(py, summary)
+    }
+}
+
+/// Outcome of an admission check
+///
+/// `kind` is one of "allowed", "rate_limited", "over_budget", "no_capacity"
+/// or "unpriced_model"; `http_status` maps it to 200/429/402/503/422.
+#[pyclass(get_all)]
+#[derive(Clone)]
+pub struct AdmissionResult {
+    kind: String,
+    allowed: bool,
+    reason: String,
+    retry_after_seconds: Option<f64>,
+    remaining_requests: u64,
+    http_status: u16,
+}
+
+impl From<rate_limiter::RateLimitResult> for AdmissionResult {
+    fn from(result: rate_limiter::RateLimitResult) -> Self {
+        Self {
+            kind: result.kind.as_str().to_string(),
+            allowed: result.allowed,
+            reason: result.reason,
+            retry_after_seconds: result.retry_after_ms.map(|ms| ms as f64 / 1000.0),
+            remaining_requests: result.remaining_requests,
+            http_status: result.kind.http_status(),
+        }
+    }
+}
+
+#[pymethods]
+impl AdmissionResult {
+    fn __bool__(&self) -> bool {
+        self.allowed
+    }
+
+    fn __repr__(&self) -> String {
+        format!(
+            "AdmissionResult(kind='{}', reason='{}', retry_after_seconds={:?})",
+            self.kind, self.reason, self.retry_after_seconds
+        )
+    }
 }
 
 /// Connection pool class for managing API connections
@@ -229,11 +701,44 @@ impl SimpleConnectionPool {
         connection_pool::get_connection(endpoint)
     }
 
+    /// Get a connection, retrying with jittered exponential backoff while the
+    /// pool is exhausted (GIL released while waiting)
+    #[pyo3(signature = (endpoint, max_attempts=5, base_delay_ms=10))]
+    fn get_connection_with_backoff(
+        &self,
+        py: Python,
+        endpoint: &str,
+        max_attempts: u32,
+        base_delay_ms: u64,
+    ) -> PyResult<String> {
+        py.allow_threads(|| {
+            connection_pool::get_connection_with_backoff(endpoint, max_attempts, base_delay_ms)
+        })
+        .ok_or_else(|| {
+            pyo3::exceptions::PyRuntimeError::new_err(format!(
+                "No connection available for '{}' after {} attempts",
+                endpoint, max_attempts
+            ))
+        })
+    }
+
     /// Return a connection to the pool
     fn return_connection(&self, connection_id: &str) {
         connection_pool::return_connection(connection_id);
     }
 
+    /// Close all connections for an endpoint, returning how many were closed
+    fn close_endpoint(&self, endpoint: &str) -> usize {
+        connection_pool::close_endpoint(endpoint)
+    }
+
+    /// List connection ids per endpoint as {available: [...], in_use: [...]}
+    #[pyo3(signature = (endpoint=None))]
+    fn list_connections(&self, py: Python, endpoint: Option<&str>) -> PyResult<PyObject> {
+        let listing = connection_pool::list_connections(endpoint);
+        convert_hashmap_to_pydict(py, listing)
+    }
+
     /// Check health of a connection
     fn health_check(&self, connection_id: &str) -> bool {
         connection_pool::health_check_connection(connection_id)
@@ -244,6 +749,25 @@ impl SimpleConnectionPool {
         connection_pool::cleanup_expired_connections();
     }
 
+    /// Peak and histogram of in-use connections for an endpoint
+    fn get_usage_histogram(&self, py: Python, endpoint: &str) -> PyResult<PyObject> {
+        let histogram = connection_pool::get_usage_histogram(endpoint);
+        convert_hashmap_to_pydict(py, histogram)
+    }
+
+    /// Reset usage histograms for an endpoint, or all endpoints
+    #[pyo3(signature = (endpoint=None))]
+    fn reset_usage_histogram(&self, endpoint: Option<&str>) {
+        connection_pool::reset_usage_histogram(endpoint);
+    }
+
+    /// Stop creating new connections for an endpoint past `limit`, while
+    /// still reusing existing ones; None removes the soft limit
+    #[pyo3(signature = (endpoint, limit=None))]
+    fn set_soft_limit(&self, endpoint: &str, limit: Option<u32>) {
+        connection_pool::set_soft_limit(endpoint, limit);
+    }
+
     /// Get pool statistics
     fn get_stats(&self, py: Python) -> PyResult<PyObject> {
         let stats = connection_pool::get_connection_pool_stats();
@@ -268,44 +792,228 @@ impl AdvancedRouter {
     }
 
     /// Get an available deployment for a model
-    #[pyo3(signature = (model_list, model, blocked_models=None))]
-    fn get_available_deployment(
+    ///
+    /// With `required_model_info`, only deployments whose `model_info`
+    /// contains every given key with an equal value are considered.
+    /// Deployments whose `model_info.id` is in `exclude_ids` are skipped.
+    #[pyo3(signature = (model_list, model, blocked_models=None, required_model_info=None, exclude_ids=None))]
+    fn get_available_deployment<'py>(
         &self,
-        py: Python,
-        model_list: Vec<PyObject>,
-        model: String,
+        model_list: Vec<Bound<'py, PyAny>>,
+        model: &str,
         blocked_models: Option<Vec<String>>,
-    ) -> PyResult<Option<PyObject>> {
-        let blocked = blocked_models.unwrap_or_default();
-        let mut available: Vec<PyObject> = Vec::new();
-
-        for item in model_list.iter() {
-            if let Ok(dict) = item.downcast_bound::<PyDict>(py) {
-                if let Ok(Some(name)) = dict.get_item("model_name") {
-                    if let Ok(name_str) = name.extract::<String>() {
-                        if name_str == model && !blocked.contains(&name_str) {
-                            available.push(item.clone_ref(py));
-                        }
-                    }
-                }
-            }
+        required_model_info: Option<Bound<'py, PyDict>>,
+        exclude_ids: Option<Vec<String>>,
+    ) -> PyResult<Option<Bound<'py, PyAny>>> {
+        pick_deployment(
+            &model_list,
+            model,
+            blocked_models,
+            exclude_ids,
+            required_model_info,
+        )
+    }
+
+    #[getter]
+    fn strategy(&self) -> &str {
+        &self.strategy
+    }
+
+    /// Add or replace a named route
+    ///
+    /// `config` takes the keys of a route config ("endpoints", "weights",
+    /// "models", "min_healthy", "adaptive_weights", "warmup_seconds", ...);
+    /// "strategy" defaults to "simple_shuffle", not this instance's strategy.
+    ///
+    /// Routes, endpoint health and metrics live in one router shared by
+    /// every `AdvancedRouter` instance in the process.
+    fn add_route(&self, name: String, config: &Bound<'_, PyDict>) -> PyResult<()> {
+        let config = extract_route_config(config)?;
+        core::add_route(name, config);
+        Ok(())
+    }
+
+    /// Pick an endpoint for a route, skipping unhealthy and `exclude`d ones
+    ///
+    /// For retries, pass the endpoints already tried as `exclude`.
+    #[pyo3(signature = (route_name, exclude=None))]
+    fn route(&self, route_name: &str, exclude: Option<Vec<String>>) -> PyResult<String> {
+        core::route_excluding(route_name, &exclude.unwrap_or_default())
+            .map_err(|e| pyo3::exceptions::PyValueError::new_err(e.to_string()))
+    }
+
+    /// Pick an endpoint whose estimated cost for the request fits `max_cost_usd`
+    ///
+    /// Output tokens are estimated from `max_tokens`, or the model's
+    /// default output share when not given.
+    #[pyo3(signature = (route_name, max_cost_usd, input_tokens, max_tokens=None))]
+    fn route_within_budget(
+        &self,
+        route_name: &str,
+        max_cost_usd: f64,
+        input_tokens: usize,
+        max_tokens: Option<usize>,
+    ) -> PyResult<String> {
+        core::route_within_budget(route_name, max_cost_usd, input_tokens, max_tokens)
+            .map_err(|e| pyo3::exceptions::PyValueError::new_err(e.to_string()))
+    }
+
+    /// Put an endpoint into cooldown
+    #[pyo3(signature = (endpoint, reason=None))]
+    fn mark_endpoint_unhealthy(&self, endpoint: &str, reason: Option<&str>) {
+        core::mark_endpoint_unhealthy(endpoint, reason);
+    }
+
+    /// Clear an endpoint's cooldown
+    fn mark_endpoint_healthy(&self, endpoint: &str) {
+        core::mark_endpoint_healthy(endpoint);
+    }
+
+    /// Record a completed request, attributing its tokens to `team` if given
+    #[pyo3(signature = (endpoint, latency_ms, success, cost, tokens=None, team=None))]
+    fn update_metrics(
+        &self,
+        endpoint: &str,
+        latency_ms: f64,
+        success: bool,
+        cost: f64,
+        tokens: Option<u64>,
+        team: Option<&str>,
+    ) {
+        match team {
+            Some(team) => core::update_metrics_tagged(
+                endpoint,
+                latency_ms,
+                success,
+                cost,
+                tokens.unwrap_or(0),
+                team,
+            ),
+            None => core::update_metrics(endpoint, latency_ms, success, cost),
         }
+    }
+
+    fn increment_active_requests(&self, endpoint: &str) {
+        core::increment_active_requests(endpoint);
+    }
+
+    fn decrement_active_requests(&self, endpoint: &str) {
+        core::decrement_active_requests(endpoint);
+    }
 
-        if !available.is_empty() {
-            let index = random_index(available.len());
-            return Ok(Some(available[index].clone_ref(py)));
+    /// Per-endpoint metrics, breaker state and cooldowns
+    fn get_metrics(&self, py: Python) -> PyResult<PyObject> {
+        convert_hashmap_to_pydict(py, core::get_metrics())
+    }
+
+    /// Per-team token and request totals for an endpoint
+    fn get_team_usage(&self, py: Python, endpoint: &str) -> PyResult<PyObject> {
+        convert_hashmap_to_pydict(py, core::get_team_usage(endpoint))
+    }
+
+    /// Record a request outcome in the endpoint's circuit breaker
+    ///
+    /// Separate from cooldowns: a breaker opens on a sustained failure rate
+    /// rather than a single event.
+    fn record_attempt(&self, endpoint: &str, success: bool) {
+        core::record_attempt(endpoint, success);
+    }
+
+    /// Current latency-tuned weights, keyed by route then endpoint
+    fn get_adaptive_weights(&self, py: Python) -> PyResult<PyObject> {
+        convert_hashmap_to_pydict(py, core::get_adaptive_weights())
+    }
+
+    /// What every strategy would pick for a route right now
+    fn compare_strategies(&self, py: Python, route_name: &str) -> PyResult<PyObject> {
+        let picks = core::compare_strategies(route_name)
+            .map_err(|e| pyo3::exceptions::PyValueError::new_err(e.to_string()))?;
+        convert_hashmap_to_pydict(py, picks)
+    }
+
+    /// Zero per-period counters; `preserve_health` keeps cooldowns and breakers
+    ///
+    /// Latency, success rate and cost averages stay warm.
+    #[pyo3(signature = (preserve_health=false))]
+    fn reset_stats(&self, preserve_health: bool) {
+        core::reset_stats(preserve_health);
+    }
+
+    /// Reset adaptive weights after a live config change; `restart_warmup`
+    /// also restarts every endpoint's warm-up ramp
+    ///
+    /// Metrics, health and usage counters are kept.
+    #[pyo3(signature = (restart_warmup=false))]
+    fn rebalance_hint(&self, restart_warmup: bool) {
+        core::rebalance_hint(restart_warmup);
+    }
+
+    /// Pick an endpoint whose model costs at most `max_output_cost_per_1m`
+    fn route_with_cost_ceiling(
+        &self,
+        route_name: &str,
+        max_output_cost_per_1m: f64,
+    ) -> PyResult<String> {
+        core::route_with_cost_ceiling(route_name, max_output_cost_per_1m)
+            .map_err(|e| pyo3::exceptions::PyValueError::new_err(e.to_string()))
+    }
+
+    /// Set the input:output token ratio assumed for a model family when
+    /// ranking endpoints without observed cost by list price
+    fn set_cost_ratio(&self, model_family: &str, ratio: f64) {
+        core::set_cost_ratio(model_family, ratio);
+    }
+
+    /// Replace the whole route set in one swap; returns (carried_over, new)
+    /// endpoint counts
+    ///
+    /// Metrics and health carry over for endpoints that are still routed to.
+    fn replace_routes(&self, routes: &Bound<'_, PyDict>) -> PyResult<(usize, usize)> {
+        let mut configs = HashMap::new();
+        for (name, config) in routes.iter() {
+            let config = extract_route_config(config.downcast::<PyDict>()?)?;
+            configs.insert(name.extract::<String>()?, config);
         }
+        Ok(core::replace_routes(configs))
+    }
 
-        // No matching model found - return None instead of silently using wrong model
-        Ok(None)
+    /// `route`, also returning a short code saying why the endpoint was picked
+    #[pyo3(signature = (route_name, exclude=None))]
+    fn route_with_reason(
+        &self,
+        route_name: &str,
+        exclude: Option<Vec<String>>,
+    ) -> PyResult<(String, &'static str)> {
+        core::route_with_reason(route_name, &exclude.unwrap_or_default())
+            .map_err(|e| pyo3::exceptions::PyValueError::new_err(e.to_string()))
     }
 
-    #[getter]
-    fn strategy(&self) -> &str {
-        &self.strategy
+    /// Current warm-up factors for routes with `warmup_seconds`, keyed by
+    /// route then endpoint
+    fn get_warmup_factors(&self, py: Python) -> PyResult<PyObject> {
+        convert_hashmap_to_pydict(py, core::get_warmup_factors())
     }
 }
 
+// Helper function to build a route config from a Python dict via its JSON form
+fn extract_route_config(config: &Bound<'_, PyDict>) -> PyResult<core::RouteConfig> {
+    let config = config.copy()?;
+    if !config.contains("strategy")? {
+        config.set_item("strategy", "simple_shuffle")?;
+    }
+    if !config.contains("weights")? {
+        config.set_item("weights", config.py().None())?;
+    }
+    let json: String = config
+        .py()
+        .import("json")?
+        .call_method1("dumps", (config,))?
+        .extract()?;
+    serde_json::from_str(&json).map_err(|e| {
+        pyo3::exceptions::PyValueError::new_err(format!("Invalid route config: {}", e))
+    })
+}
+
 // ============================================================
 // Standalone Functions
 // ============================================================
@@ -363,6 +1071,25 @@ fn reset_errors(feature_name: Option<String>) {
     feature_flags::reset_feature_errors(feature_name.as_deref());
 }
 
+/// Clear a feature's errors automatically after a quiet period
+///
+/// Pass `None` to go back to manual resets only. Returns False if the
+/// feature is unknown.
+#[pyfunction]
+#[pyo3(signature = (feature_name, seconds=None))]
+fn set_auto_reset(feature_name: &str, seconds: Option<u64>) -> bool {
+    feature_flags::set_feature_auto_reset(feature_name, seconds)
+}
+
+/// Switch a feature fully on or off
+///
+/// E.g. `set_feature_enabled("connection_pool_timing", False)` stops timing
+/// connection checkouts. Returns False if the feature is unknown.
+#[pyfunction]
+fn set_feature_enabled(feature_name: &str, enabled: bool) -> bool {
+    feature_flags::set_feature_enabled(feature_name, enabled)
+}
+
 /// Record performance metrics
 #[pyfunction]
 #[pyo3(signature = (component, operation, duration_ms, success=None, input_size=None, output_size=None))]
@@ -393,6 +1120,46 @@ fn get_performance_stats(py: Python, component: Option<String>) -> PyResult<PyOb
     convert_hashmap_to_pydict(py, stats)
 }
 
+/// Snapshot performance counters (opaque string for `performance_diff_since`)
+#[pyfunction]
+fn performance_snapshot() -> String {
+    performance_monitor::snapshot_performance()
+}
+
+/// Per-(component, operation) performance deltas since a snapshot
+#[pyfunction]
+fn performance_diff_since(py: Python, snapshot: &str) -> PyResult<PyObject> {
+    let diff = performance_monitor::diff_performance_since(snapshot)
+        .map_err(pyo3::exceptions::PyValueError::new_err)?;
+    convert_hashmap_to_pydict(py, diff)
+}
+
+/// Seed the random deployment picks, or restore unseeded picks with None
+///
+/// With a seed, the same model list and call sequence always yields the same
+/// deployments, which keeps routing snapshot tests reproducible. Also seeds
+/// the shuffle strategies of `AdvancedRouter` routes.
+#[pyfunction]
+#[pyo3(signature = (seed=None))]
+fn set_routing_seed(seed: Option<u64>) {
+    *ROUTING_RNG.lock().unwrap_or_else(|e| e.into_inner()) = seed.map(StdRng::seed_from_u64);
+    core::set_seed(seed);
+}
+
+/// Map a key to a stable bucket in `[0, buckets)`
+///
+/// Uses the same hash as feature-flag rollouts, so Python callers get the
+/// same bucket for a key across processes, runs and releases.
+#[pyfunction]
+fn hash_bucket(key: &str, buckets: u64) -> PyResult<u64> {
+    if buckets == 0 {
+        return Err(pyo3::exceptions::PyValueError::new_err(
+            "buckets must be greater than zero",
+        ));
+    }
+    Ok(hashing::hash_bucket(key, buckets))
+}
+
 /// Get pricing status and metrics
 #[pyfunction]
 fn get_pricing_status(py: Python) -> PyResult<PyObject> {
@@ -400,6 +1167,46 @@ fn get_pricing_status(py: Python) -> PyResult<PyObject> {
     convert_json_value_to_py(py, status)
 }
 
+/// Get the full pricing entry for a model, including untyped extra fields
+#[pyfunction]
+fn get_model_pricing(py: Python, model: String) -> PyResult<Option<PyObject>> {
+    match pricing::get_pricing_data().find_pricing(&model) {
+        Some(model_pricing) => {
+            let value = serde_json::to_value(model_pricing)
+                .map_err(|e| pyo3::exceptions::PyValueError::new_err(e.to_string()))?;
+            Ok(Some(convert_json_value_to_py(py, value)?))
+        }
+        None => Ok(None),
+    }
+}
+
+/// Report which models have pricing data and which fall back to defaults
+///
+/// Returns a dict with `exact`, `normalized` (model -> resolved pricing key)
+/// and `defaulted` entries so pricing gaps can be flagged at startup.
+#[pyfunction]
+fn check_pricing_coverage(py: Python, models: Vec<String>) -> PyResult<PyObject> {
+    let coverage = pricing::get_pricing_data().check_coverage(&models);
+    convert_json_value_to_py(py, coverage)
+}
+
+/// Compute the cost of a completed request from its OpenAI-style `usage`
+///
+/// Reads `prompt_tokens`, `completion_tokens` and, if present,
+/// `prompt_tokens_details.cached_tokens`; accepts a dict or a usage object.
+#[pyfunction]
+fn cost_from_usage(model: &str, usage: &Bound<'_, PyAny>) -> PyResult<f64> {
+    let prompt_tokens = get_usage_count(usage, "prompt_tokens")?;
+    let completion_tokens = get_usage_count(usage, "completion_tokens")?;
+    let cached_tokens = match get_usage_field(usage, "prompt_tokens_details")? {
+        Some(details) => get_usage_count(&details, "cached_tokens")?,
+        None => 0,
+    };
+
+    tokens::cost_from_usage(model, prompt_tokens, completion_tokens, cached_tokens)
+        .map_err(pyo3::exceptions::PyValueError::new_err)
+}
+
 /// Compare implementations
 #[pyfunction]
 fn compare_implementations(
@@ -460,22 +1267,63 @@ fn get_patch_status(py: Python) -> PyResult<PyObject> {
     Ok(dict.into())
 }
 
+/// Dump the runtime state of every global component as one JSON document
+///
+/// Intended for support bundles. Everything reflects the shared global
+/// state, including the routes and endpoint metrics behind `AdvancedRouter`.
+#[pyfunction]
+fn dump_state() -> PyResult<String> {
+    let state = serde_json::json!({
+        "timestamp_ms": std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_millis() as u64,
+        "rate_limiter": {
+            "summary": rate_limiter::get_rate_limit_summary(),
+            "keys": rate_limiter::get_rate_limit_stats(),
+        },
+        "connection_pool": connection_pool::get_connection_pool_stats(),
+        "router": core::snapshot(),
+        "pricing": pricing::get_pricing_status(),
+        "feature_flags": feature_flags::get_all_feature_status(),
+        "performance": performance_monitor::get_performance_stats(None),
+    });
+
+    serde_json::to_string_pretty(&state)
+        .map_err(|e| pyo3::exceptions::PyValueError::new_err(e.to_string()))
+}
+
 // ============================================================
 // Rate Limiter Functions (exposed to Python)
 // ============================================================
 
 /// Check if a request is allowed under rate limits
+///
+/// Non-blocking by default; with `max_wait_ms` it waits up to that long for a
+/// slot to free up before rejecting. `cost` weights the request (default 1).
 #[pyfunction]
-fn check_rate_limit(py: Python, key: String) -> PyResult<PyObject> {
-    let result = rate_limiter::check_rate_limit(&key);
-    let dict = PyDict::new(py);
-    dict.set_item("allowed", result.allowed)?;
-    dict.set_item("reason", result.reason)?;
-    dict.set_item("remaining_requests", result.remaining_requests)?;
-    if let Some(retry_after) = result.retry_after_ms {
-        dict.set_item("retry_after_ms", retry_after)?;
-    }
-    Ok(dict.into())
+#[pyo3(signature = (key, max_wait_ms=None, cost=None))]
+fn check_rate_limit(
+    py: Python,
+    key: String,
+    max_wait_ms: Option<u64>,
+    cost: Option<u64>,
+) -> PyResult<PyObject> {
+    let cost = cost.unwrap_or(1);
+    let result = match max_wait_ms {
+        Some(max_wait_ms) => {
+            py.allow_threads(|| rate_limiter::check_rate_limit_blocking(&key, max_wait_ms, cost))
+        }
+        None => rate_limiter::check_rate_limit_with_cost(&key, cost),
+    };
+    rate_limit_result_to_pydict(py, result)
+}
+
+/// Check hierarchical (org -> project -> key) limits atomically
+#[pyfunction]
+fn check_rate_limit_group(py: Python, levels: Vec<(String, u64, u64)>) -> PyResult<PyObject> {
+    let result = rate_limiter::check_rate_limit_group(&levels);
+    rate_limit_result_to_pydict(py, result)
 }
 
 /// Get rate limit statistics
@@ -532,42 +1380,92 @@ fn get_connection_pool_stats(py: Python) -> PyResult<PyObject> {
 
 /// Get an available deployment for a model
 /// This is a simplified version that demonstrates the routing capability
+///
+/// Deployments whose `model_info.id` is in `exclude_ids` are skipped, so a
+/// retry loop can walk distinct deployments. With `required_model_info`,
+/// only deployments whose `model_info` contains every given key with an
+/// equal value are considered. Returns None if nothing matches, so the
+/// caller has to handle it explicitly.
 #[pyfunction]
-#[pyo3(signature = (model_list, model, blocked_models=None, _context=None, _settings=None))]
-fn get_available_deployment(
-    py: Python,
-    model_list: Vec<PyObject>,
-    model: String,
+#[pyo3(signature = (model_list, model, blocked_models=None, _context=None, _settings=None, exclude_ids=None, required_model_info=None))]
+fn get_available_deployment<'py>(
+    model_list: Vec<Bound<'py, PyAny>>,
+    model: &str,
     blocked_models: Option<Vec<String>>,
     _context: Option<PyObject>,
     _settings: Option<PyObject>,
-) -> PyResult<Option<PyObject>> {
-    // Filter model_list to find matching models
-    let mut available: Vec<PyObject> = Vec::new();
-    let blocked = blocked_models.unwrap_or_default();
-
-    for item in model_list.iter() {
-        // Extract model_name from dict with validation
-        if let Ok(dict) = item.downcast_bound::<PyDict>(py) {
-            if let Ok(Some(name)) = dict.get_item("model_name") {
-                if let Ok(name_str) = name.extract::<String>() {
-                    if name_str == model && !blocked.contains(&name_str) {
-                        available.push(item.clone_ref(py));
-                    }
-                }
-            }
+    exclude_ids: Option<Vec<String>>,
+    required_model_info: Option<Bound<'py, PyDict>>,
+) -> PyResult<Option<Bound<'py, PyAny>>> {
+    pick_deployment(
+        &model_list,
+        model,
+        blocked_models,
+        exclude_ids,
+        required_model_info,
+    )
+}
+
+/// Estimate response tokens for a request dict
+///
+/// Uses `max_completion_tokens`, then `max_tokens`, then the default output
+/// fraction of the model's context window.
+#[pyfunction]
+fn estimate_output_tokens(request_data: &Bound<'_, PyDict>, model: String) -> PyResult<usize> {
+    let read_limit = |key: &str| -> PyResult<Option<usize>> {
+        match request_data.get_item(key)? {
+            Some(value) if !value.is_none() => Ok(Some(value.extract()?)),
+            _ => Ok(None),
         }
-    }
+    };
 
-    // Return a matching model if found
-    if !available.is_empty() {
-        let index = random_index(available.len());
-        return Ok(Some(available[index].clone_ref(py)));
-    }
+    Ok(tokens::estimate_output_tokens(
+        &model,
+        read_limit("max_tokens")?,
+        read_limit("max_completion_tokens")?,
+    ))
+}
+
+/// Set the share of the context window assumed for responses without a limit
+#[pyfunction]
+fn set_default_output_fraction(fraction: f64) {
+    tokens::set_default_output_fraction(fraction);
+}
+
+/// Set the largest input counted exactly; larger inputs are approximated
+///
+/// Pass None to remove the limit.
+#[pyfunction]
+#[pyo3(signature = (max_bytes=None))]
+fn set_max_encode_bytes(max_bytes: Option<usize>) {
+    tokens::set_max_encode_bytes(max_bytes);
+}
+
+// ============================================================
+// Token Encoding Functions (exposed to Python)
+// ============================================================
+
+/// Map a model to a tiktoken encoding ahead of the built-in lookup
+#[pyfunction]
+fn register_model_encoding(model: &str, encoding: &str) -> PyResult<()> {
+    tokens::register_model_encoding(model, encoding)
+        .map_err(pyo3::exceptions::PyValueError::new_err)
+}
+
+/// Remove all runtime model-to-encoding overrides
+#[pyfunction]
+fn clear_model_encoding_overrides() {
+    tokens::clear_model_encoding_overrides();
+}
+
+// ============================================================
+// Text Utilities (exposed to Python)
+// ============================================================
 
-    // No matching model found - return None instead of silently using wrong model
-    // This forces the caller to handle the case explicitly
-    Ok(None)
+/// Truncate text to a byte budget without splitting a UTF-8 character
+#[pyfunction]
+fn truncate_to_bytes(text: &str, max_bytes: usize) -> String {
+    tokens::truncate_to_bytes(text, max_bytes)
 }
 
 /// Python module definition
@@ -587,18 +1485,29 @@ fn _rust(m: &Bound<'_, PyModule>) -> PyResult<()> {
     m.add_function(wrap_pyfunction!(is_enabled, m)?)?;
     m.add_function(wrap_pyfunction!(get_feature_status, m)?)?;
     m.add_function(wrap_pyfunction!(reset_errors, m)?)?;
+    m.add_function(wrap_pyfunction!(set_auto_reset, m)?)?;
+    m.add_function(wrap_pyfunction!(set_feature_enabled, m)?)?;
 
     // Performance monitoring functions
     m.add_function(wrap_pyfunction!(record_performance, m)?)?;
     m.add_function(wrap_pyfunction!(get_performance_stats, m)?)?;
+    m.add_function(wrap_pyfunction!(performance_snapshot, m)?)?;
+    m.add_function(wrap_pyfunction!(performance_diff_since, m)?)?;
     m.add_function(wrap_pyfunction!(get_pricing_status, m)?)?;
+    m.add_function(wrap_pyfunction!(get_model_pricing, m)?)?;
+    m.add_function(wrap_pyfunction!(check_pricing_coverage, m)?)?;
+    m.add_function(wrap_pyfunction!(hash_bucket, m)?)?;
+    m.add_function(wrap_pyfunction!(set_routing_seed, m)?)?;
+    m.add_function(wrap_pyfunction!(cost_from_usage, m)?)?;
     m.add_function(wrap_pyfunction!(compare_implementations, m)?)?;
     m.add_function(wrap_pyfunction!(get_recommendations, m)?)?;
     m.add_function(wrap_pyfunction!(export_performance_data, m)?)?;
     m.add_function(wrap_pyfunction!(get_patch_status, m)?)?;
+    m.add_function(wrap_pyfunction!(dump_state, m)?)?;
 
     // Rate limiter functions
     m.add_function(wrap_pyfunction!(check_rate_limit, m)?)?;
+    m.add_function(wrap_pyfunction!(check_rate_limit_group, m)?)?;
     m.add_function(wrap_pyfunction!(get_rate_limit_stats, m)?)?;
 
     // Connection pool functions
@@ -611,10 +1520,21 @@ fn _rust(m: &Bound<'_, PyModule>) -> PyResult<()> {
 
     // Routing functions
     m.add_function(wrap_pyfunction!(get_available_deployment, m)?)?;
+    m.add_function(wrap_pyfunction!(estimate_output_tokens, m)?)?;
+    m.add_function(wrap_pyfunction!(set_default_output_fraction, m)?)?;
+    m.add_function(wrap_pyfunction!(set_max_encode_bytes, m)?)?;
+
+    // Token encoding functions
+    m.add_function(wrap_pyfunction!(register_model_encoding, m)?)?;
+    m.add_function(wrap_pyfunction!(clear_model_encoding_overrides, m)?)?;
+
+    // Text utilities
+    m.add_function(wrap_pyfunction!(truncate_to_bytes, m)?)?;
 
     // Add classes for shimming
     m.add_class::<SimpleTokenCounter>()?;
     m.add_class::<SimpleRateLimiter>()?;
+    m.add_class::<AdmissionResult>()?;
     m.add_class::<SimpleConnectionPool>()?;
     m.add_class::<AdvancedRouter>()?;
 
@@ -120,6 +120,8 @@ impl ComponentStats {
 pub struct PerformanceMonitor {
     metrics: DashMap<String, Vec<PerformanceMetric>>,
     component_stats: DashMap<String, ComponentStats>,
+    /// "component:operation" -> cumulative stats, used for snapshots/diffs
+    operation_stats: DashMap<String, ComponentStats>,
 }
 
 impl Default for PerformanceMonitor {
@@ -133,6 +135,7 @@ impl PerformanceMonitor {
         Self {
             metrics: DashMap::new(),
             component_stats: DashMap::new(),
+            operation_stats: DashMap::new(),
         }
     }
 
@@ -146,6 +149,11 @@ impl PerformanceMonitor {
             .or_default();
         stats.update(metric.duration_ms, metric.success);
 
+        self.operation_stats
+            .entry(key.clone())
+            .or_default()
+            .update(metric.duration_ms, metric.success);
+
         // Store detailed metric (limit to last 1000 entries per key)
         let mut metrics = self.metrics.entry(key).or_default();
         metrics.push(metric);
@@ -154,6 +162,29 @@ impl PerformanceMonitor {
         }
     }
 
+    /// Update an operation's aggregate stats without keeping a detailed
+    /// metric, for hot paths where building and storing one costs too much
+    pub fn record_duration(
+        &self,
+        component: &str,
+        operation: &str,
+        duration_ms: f64,
+        success: bool,
+    ) {
+        match self.component_stats.get(component) {
+            Some(stats) => stats.update(duration_ms, success),
+            None => self
+                .component_stats
+                .entry(component.to_string())
+                .or_default()
+                .update(duration_ms, success),
+        }
+        self.operation_stats
+            .entry(format!("{}:{}", component, operation))
+            .or_default()
+            .update(duration_ms, success);
+    }
+
     pub fn get_stats(&self, component: Option<&str>) -> HashMap<String, serde_json::Value> {
         let mut result = HashMap::new();
 
@@ -170,6 +201,93 @@ impl PerformanceMonitor {
         result
     }
 
+    /// Capture cumulative per-(component, operation) counters as an opaque string
+    ///
+    /// Pass the result to `diff_since` later to get the activity in between,
+    /// without resetting the global stats other callers rely on.
+    pub fn snapshot(&self) -> String {
+        let operations: serde_json::Map<String, serde_json::Value> = self
+            .operation_stats
+            .iter()
+            .map(|entry| {
+                let stats = entry.value();
+                (
+                    entry.key().clone(),
+                    serde_json::json!({
+                        "total_calls": stats.total_calls.load(Ordering::Relaxed),
+                        "total_duration_us": stats.total_duration_ms.load(Ordering::Relaxed),
+                        "success_count": stats.success_count.load(Ordering::Relaxed),
+                        "error_count": stats.error_count.load(Ordering::Relaxed)
+                    }),
+                )
+            })
+            .collect();
+
+        serde_json::json!({ "version": 1, "operations": operations }).to_string()
+    }
+
+    /// Per-(component, operation) deltas since a `snapshot`
+    pub fn diff_since(&self, snapshot: &str) -> Result<HashMap<String, serde_json::Value>, String> {
+        let baseline: serde_json::Value =
+            serde_json::from_str(snapshot).map_err(|e| format!("Invalid snapshot: {}", e))?;
+        let baseline = baseline
+            .get("operations")
+            .and_then(|v| v.as_object())
+            .ok_or_else(|| "Invalid snapshot: missing operations".to_string())?;
+
+        let mut result = HashMap::new();
+        for entry in self.operation_stats.iter() {
+            let stats = entry.value();
+            let before = baseline.get(entry.key());
+            let field = |name: &str| {
+                before
+                    .and_then(|b| b.get(name))
+                    .and_then(|v| v.as_u64())
+                    .unwrap_or(0)
+            };
+
+            let calls = stats
+                .total_calls
+                .load(Ordering::Relaxed)
+                .saturating_sub(field("total_calls"));
+            if calls == 0 {
+                continue;
+            }
+            let duration_us = stats
+                .total_duration_ms
+                .load(Ordering::Relaxed)
+                .saturating_sub(field("total_duration_us"));
+            let successes = stats
+                .success_count
+                .load(Ordering::Relaxed)
+                .saturating_sub(field("success_count"));
+            let errors = stats
+                .error_count
+                .load(Ordering::Relaxed)
+                .saturating_sub(field("error_count"));
+
+            let (component, operation) = entry
+                .key()
+                .split_once(':')
+                .unwrap_or((entry.key().as_str(), ""));
+
+            result.insert(
+                entry.key().clone(),
+                serde_json::json!({
+                    "component": component,
+                    "operation": operation,
+                    "calls": calls,
+                    "success_count": successes,
+                    "error_count": errors,
+                    "total_duration_ms": duration_us as f64 / 1000.0,
+                    "average_duration_ms": duration_us as f64 / calls as f64 / 1000.0
+                }),
+            );
+        }
+
+        Ok(result)
+    }
+
     pub fn compare_implementations(
         &self,
         rust_component: &str,
@@ -397,10 +515,24 @@ pub fn record_performance(
     PERFORMANCE_MONITOR.record(metric);
 }
 
+pub fn record_duration(component: &str, operation: &str, duration_ms: f64, success: bool) {
+    PERFORMANCE_MONITOR.record_duration(component, operation, duration_ms, success);
+}
+
 pub fn get_performance_stats(component: Option<&str>) -> HashMap<String, serde_json::Value> {
     PERFORMANCE_MONITOR.get_stats(component)
 }
 
+pub fn snapshot_performance() -> String {
+    PERFORMANCE_MONITOR.snapshot()
+}
+
+pub fn diff_performance_since(
+    snapshot: &str,
+) -> Result<HashMap<String, serde_json::Value>, String> {
+    PERFORMANCE_MONITOR.diff_since(snapshot)
+}
+
 pub fn compare_implementations(
     rust_component: &str,
     python_component: &str,
@@ -415,3 +547,64 @@ pub fn get_recommendations() -> Vec<HashMap<String, serde_json::Value>> {
 pub fn export_performance_data(component: Option<&str>, format: &str) -> String {
     PERFORMANCE_MONITOR.export_data(component, format)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn metric(
+        component: &str,
+        operation: &str,
+        duration_ms: f64,
+        success: bool,
+    ) -> PerformanceMetric {
+        PerformanceMetric {
+            component: component.to_string(),
+            operation: operation.to_string(),
+            duration_ms,
+            success,
+            input_size: None,
+            output_size: None,
+            timestamp: 0,
+            metadata: None,
+        }
+    }
+
+    #[test]
+    fn test_diff_since_snapshot() {
+        let monitor = PerformanceMonitor::new();
+        monitor.record(metric("tokens", "count", 5.0, true));
+        monitor.record(metric("routing", "route", 1.0, true));
+        let snapshot = monitor.snapshot();
+
+        monitor.record(metric("tokens", "count", 2.0, true));
+        monitor.record(metric("tokens", "count", 4.0, false));
+        monitor.record(metric("pool", "get", 1.5, true));
+
+        let diff = monitor.diff_since(&snapshot).unwrap();
+        assert_eq!(diff.len(), 2, "idle operations are left out");
+        let count = &diff["tokens:count"];
+        assert_eq!(count["component"], "tokens");
+        assert_eq!(count["operation"], "count");
+        assert_eq!(count["calls"], 2);
+        assert_eq!(count["success_count"], 1);
+        assert_eq!(count["error_count"], 1);
+        assert_eq!(count["total_duration_ms"], 6.0);
+        assert_eq!(count["average_duration_ms"], 3.0);
+        assert_eq!(diff["pool:get"]["calls"], 1);
+
+        // Snapshots don't reset the cumulative stats
+        assert_eq!(
+            monitor.get_stats(Some("tokens"))["tokens"]["total_calls"],
+            3
+        );
+        assert!(monitor.diff_since(&monitor.snapshot()).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_diff_since_rejects_bad_snapshots() {
+        let monitor = PerformanceMonitor::new();
+        assert!(monitor.diff_since("not json").is_err());
+        assert!(monitor.diff_since("{\"version\": 1}").is_err());
+    }
+}
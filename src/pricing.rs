@@ -7,7 +7,7 @@
 //! ```
 
 use dashmap::DashMap;
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::fs;
 use std::path::PathBuf;
@@ -52,7 +52,7 @@ impl PricingStats {
 }
 
 /// Pricing data structure from LiteLLM's model_prices_and_context_window.json
-#[derive(Debug, Clone, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ModelPricing {
     pub litellm_provider: Option<String>,
     pub mode: Option<String>,
@@ -67,17 +67,55 @@ pub struct ModelPricing {
     #[serde(default)]
     pub output_cost_per_token: Option<f64>,
     #[serde(default)]
+    pub cache_read_input_token_cost: Option<f64>,
+    #[serde(default)]
     pub output_cost_per_image: Option<f64>,
     #[serde(default)]
     pub input_cost_per_pixel: Option<f64>,
+    /// Every other field from the pricing JSON (e.g. `supports_audio_input`,
+    /// `rpm`, `deprecation_date`), kept as-is
+    #[serde(flatten, default)]
+    pub extra: HashMap<String, serde_json::Value>,
+}
+
+impl ModelPricing {
+    /// Total context window
+    ///
+    /// Entries with split limits sum `max_input_tokens` and
+    /// `max_output_tokens`; legacy entries with only `max_tokens` use it as
+    /// the total. `max_tokens` is never added on top of the split fields.
+    pub fn context_window(&self) -> Option<u32> {
+        let window = if self.max_input_tokens.is_some() || self.max_output_tokens.is_some() {
+            self.max_input_tokens
+                .unwrap_or(0)
+                .saturating_add(self.max_output_tokens.unwrap_or(0))
+        } else {
+            self.max_tokens.unwrap_or(0)
+        };
+        (window > 0).then_some(window)
+    }
 }
 
+/// Pricing JSON fields with a typed slot in `ModelPricing`
+const TYPED_PRICING_FIELDS: [&str; 10] = [
+    "litellm_provider",
+    "mode",
+    "max_input_tokens",
+    "max_output_tokens",
+    "max_tokens",
+    "input_cost_per_token",
+    "output_cost_per_token",
+    "cache_read_input_token_cost",
+    "output_cost_per_image",
+    "input_cost_per_pixel",
+];
+
 /// Loaded pricing data
 pub struct PricingData {
     /// Map of model name -> pricing info
     pub models: HashMap<String, ModelPricing>,
-    /// Cache for fast lookups: input model -> whether found
-    lookup_cache: DashMap<String, bool>,
+    /// Cache for fast lookups: input model -> resolved model key (None if not found)
+    lookup_cache: DashMap<String, Option<String>>,
 }
 
 impl PricingData {
@@ -97,45 +135,41 @@ impl PricingData {
 
     /// Find pricing for a model, trying various name normalizations
     pub fn find_pricing(&self, model: &str) -> Option<&ModelPricing> {
-        // Check cache first (cache miss results)
-        if let Some(found) = self.lookup_cache.get(model) {
-            if !*found {
+        // Check cache first (includes cached misses)
+        if let Some(resolved) = self.lookup_cache.get(model) {
+            let result = resolved.as_ref().and_then(|key| self.models.get(key));
+            if result.is_none() {
                 // Record lookup failure for unknown model
                 get_pricing_stats().record_lookup_failure();
-                return None;
             }
-            return Some(
-                self.models
-                    .get(model)
-                    .expect("cache entry should exist if marked as found"),
-            );
+            return result;
         }
 
-        // Find the pricing
-        let result = self.find_pricing_uncached(model);
+        let resolved = self.resolve_model_key(model);
 
         // Cache the result
         self.lookup_cache
-            .insert(model.to_string(), result.is_some());
+            .insert(model.to_string(), resolved.map(String::from));
 
         // Record failure if not found
-        if result.is_none() {
+        if resolved.is_none() {
             get_pricing_stats().record_lookup_failure();
         }
 
-        result
+        resolved.and_then(|key| self.models.get(key))
     }
 
-    fn find_pricing_uncached(&self, model: &str) -> Option<&ModelPricing> {
+    /// Resolve a model name to the key it is stored under in the pricing data
+    fn resolve_model_key(&self, model: &str) -> Option<&str> {
         // Direct match
-        if let Some(pricing) = self.models.get(model) {
-            return Some(pricing);
+        if let Some((key, _)) = self.models.get_key_value(model) {
+            return Some(key);
         }
 
         // Try without provider prefix (e.g., "gpt-4" from "azure/gpt-4")
         if let Some(slash_pos) = model.find('/') {
             let without_prefix = &model[slash_pos + 1..];
-            if let Some(pricing) = self.models.get(without_prefix) {
+            if let Some((key, pricing)) = self.models.get_key_value(without_prefix) {
                 // Make sure it's not chat+completion mode confusion
                 if pricing
                     .mode
@@ -143,29 +177,51 @@ impl PricingData {
                     .map(|m| m.contains("chat"))
                     .unwrap_or(true)
                 {
-                    return Some(pricing);
+                    return Some(key);
                 }
             }
         }
 
         // Try with common provider prefixes
-        if let Some(pricing) = self.models.get(&format!("openai/{}", model)) {
-            return Some(pricing);
-        }
-        if let Some(pricing) = self.models.get(&format!("azure/{}", model)) {
-            return Some(pricing);
-        }
-        if let Some(pricing) = self.models.get(&format!("anthropic.{}", model)) {
-            return Some(pricing);
-        }
-        if let Some(pricing) = self.models.get(&format!("google/{}", model)) {
-            return Some(pricing);
-        }
-        if let Some(pricing) = self.models.get(&format!("bedrock/{}", model)) {
-            return Some(pricing);
+        let candidates = [
+            format!("openai/{}", model),
+            format!("azure/{}", model),
+            format!("anthropic.{}", model),
+            format!("google/{}", model),
+            format!("bedrock/{}", model),
+        ];
+        candidates
+            .iter()
+            .find_map(|candidate| self.models.get_key_value(candidate))
+            .map(|(key, _)| key.as_str())
+    }
+
+    /// Classify how each model resolves against the pricing data
+    ///
+    /// Models are reported as `exact` (stored under that name), `normalized`
+    /// (found via a prefix normalization, mapped to the key it resolved to) or
+    /// `defaulted` (not found, so cost estimates use the built-in defaults).
+    /// Does not touch the lookup cache or failure stats.
+    pub fn check_coverage(&self, models: &[String]) -> serde_json::Value {
+        let mut exact = Vec::new();
+        let mut normalized = serde_json::Map::new();
+        let mut defaulted = Vec::new();
+
+        for model in models {
+            match self.resolve_model_key(model) {
+                Some(key) if key == model => exact.push(model.clone()),
+                Some(key) => {
+                    normalized.insert(model.clone(), serde_json::Value::from(key));
+                }
+                None => defaulted.push(model.clone()),
+            }
         }
 
-        None
+        serde_json::json!({
+            "exact": exact,
+            "normalized": normalized,
+            "defaulted": defaulted,
+        })
     }
 
     /// Get input cost per 1M tokens for a model
@@ -182,17 +238,17 @@ impl PricingData {
             .map(|cost| cost * 1_000_000.0)
     }
 
+    /// Get cached (prompt cache read) input cost per 1M tokens for a model
+    pub fn get_cached_input_cost_per_1m(&self, model: &str) -> Option<f64> {
+        self.find_pricing(model)
+            .and_then(|p| p.cache_read_input_token_cost)
+            .map(|cost| cost * 1_000_000.0)
+    }
+
     /// Get context window (max input + max output) for a model
     pub fn get_context_window(&self, model: &str) -> Option<u32> {
-        self.find_pricing(model).and_then(|p| {
-            let input = p.max_input_tokens.unwrap_or(0);
-            let output = p.max_output_tokens.or(p.max_tokens).unwrap_or(0);
-            if input > 0 || output > 0 {
-                Some(input + output)
-            } else {
-                None
-            }
-        })
+        self.find_pricing(model)
+            .and_then(ModelPricing::context_window)
     }
 
     /// Get max output tokens for a model
@@ -270,12 +326,22 @@ fn load_pricing_data() -> PricingData {
                                         output_cost_per_token: pricing_info
                                             .get("output_cost_per_token")
                                             .and_then(|v| v.as_f64()),
+                                        cache_read_input_token_cost: pricing_info
+                                            .get("cache_read_input_token_cost")
+                                            .and_then(|v| v.as_f64()),
                                         output_cost_per_image: pricing_info
                                             .get("output_cost_per_image")
                                             .and_then(|v| v.as_f64()),
                                         input_cost_per_pixel: pricing_info
                                             .get("input_cost_per_pixel")
                                             .and_then(|v| v.as_f64()),
+                                        extra: pricing_info
+                                            .iter()
+                                            .filter(|(key, _)| {
+                                                !TYPED_PRICING_FIELDS.contains(&key.as_str())
+                                            })
+                                            .map(|(key, value)| (key.clone(), value.clone()))
+                                            .collect(),
                                     };
 
                                     // Only insert if it has chat/completion mode or has cost info
@@ -356,10 +422,47 @@ pub fn get_pricing_status() -> serde_json::Value {
     })
 }
 
+/// Provider-typical default pricing keyed off the `provider/` prefix of a model name
+///
+/// Returns `None` when the provider (or, for Bedrock, the model vendor) is not
+/// recognized so the caller can fall back to model-name substring matching.
+fn default_pricing_for_provider(provider: &str, model_name: &str) -> Option<(f64, f64)> {
+    match provider {
+        "mistral" => Some((2.0, 6.0)),                  // $2/$6 per 1M
+        "cohere" | "cohere_chat" => Some((2.5, 10.0)),  // $2.50/$10 per 1M
+        "groq" => Some((0.6, 0.8)),                     // $0.60/$0.80 per 1M
+        "together_ai" | "together" => Some((0.9, 0.9)), // $0.90/$0.90 per 1M
+        "bedrock" => {
+            // Bedrock model ids are "<vendor>.<model>", price by vendor family
+            let vendor = model_name.split('.').next().unwrap_or("");
+            match vendor {
+                "anthropic" => Some((3.0, 15.0)),
+                "meta" => Some((0.7, 0.9)),
+                "mistral" => Some((2.0, 6.0)),
+                "cohere" => Some((2.5, 10.0)),
+                "amazon" => Some((0.8, 3.2)),
+                "ai21" => Some((2.0, 8.0)),
+                // Unknown vendor: let the model-name defaults apply
+                _ => None,
+            }
+        }
+        _ => None,
+    }
+}
+
 /// Default pricing for unknown models (fallback)
+///
+/// The provider prefix (e.g. `mistral/`, `groq/`) is consulted first; model
+/// name substrings are used when no provider default applies.
 pub fn default_pricing_for_model(model: &str) -> (f64, f64) {
     let model_lower = model.to_lowercase();
 
+    if let Some((provider, model_name)) = model_lower.split_once('/') {
+        if let Some(pricing) = default_pricing_for_provider(provider, model_name) {
+            return pricing;
+        }
+    }
+
     // GPT-4 class pricing default
     if model_lower.contains("gpt-4") {
         (30.0, 60.0) // $30/$60 per 1M
@@ -396,3 +499,114 @@ pub fn default_context_window_for_model(model: &str) -> u32 {
         4096 // 4k default
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pricing(json: serde_json::Value) -> ModelPricing {
+        serde_json::from_value(json).unwrap()
+    }
+
+    #[test]
+    fn test_check_coverage() {
+        let mut data = PricingData::new();
+        data.insert("gpt-4o".to_string(), pricing(serde_json::json!({})));
+        data.insert(
+            "openai/gpt-4o-mini".to_string(),
+            pricing(serde_json::json!({})),
+        );
+
+        let models = ["gpt-4o", "azure/gpt-4o", "gpt-4o-mini", "unknown-model"].map(String::from);
+        let coverage = data.check_coverage(&models);
+        assert_eq!(coverage["exact"], serde_json::json!(["gpt-4o"]));
+        assert_eq!(
+            coverage["normalized"],
+            serde_json::json!({"azure/gpt-4o": "gpt-4o", "gpt-4o-mini": "openai/gpt-4o-mini"})
+        );
+        assert_eq!(coverage["defaulted"], serde_json::json!(["unknown-model"]));
+    }
+
+    #[test]
+    fn test_cached_normalized_lookup() {
+        let mut data = PricingData::new();
+        data.insert(
+            "gpt-4".to_string(),
+            pricing(serde_json::json!({"mode": "chat", "input_cost_per_token": 0.00003})),
+        );
+
+        // The second lookup is served from the cache of resolved keys
+        for _ in 0..2 {
+            let found = data.find_pricing("azure/gpt-4").unwrap();
+            assert_eq!(found.input_cost_per_token, Some(0.00003));
+        }
+        assert!(data.find_pricing("azure/unknown-model").is_none());
+        assert!(data.find_pricing("azure/unknown-model").is_none());
+    }
+
+    #[test]
+    fn test_default_pricing_by_provider() {
+        let cases = [
+            ("mistral/mistral-large", (2.0, 6.0)),
+            ("cohere/command-r", (2.5, 10.0)),
+            ("cohere_chat/command-r", (2.5, 10.0)),
+            ("groq/llama3-70b", (0.6, 0.8)),
+            ("together_ai/llama-3-70b", (0.9, 0.9)),
+            ("together/llama-3-70b", (0.9, 0.9)),
+            ("bedrock/anthropic.claude-3-sonnet", (3.0, 15.0)),
+            ("bedrock/meta.llama3-70b", (0.7, 0.9)),
+            ("bedrock/mistral.mistral-large", (2.0, 6.0)),
+            ("bedrock/cohere.command-r", (2.5, 10.0)),
+            ("bedrock/amazon.titan-text", (0.8, 3.2)),
+            ("bedrock/ai21.jamba", (2.0, 8.0)),
+            ("Groq/Llama3-70b", (0.6, 0.8)),
+        ];
+        for (model, expected) in cases {
+            assert_eq!(default_pricing_for_model(model), expected, "{}", model);
+        }
+    }
+
+    #[test]
+    fn test_default_pricing_falls_back_to_model_name() {
+        let cases = [
+            // Unknown providers and Bedrock vendors use the name defaults
+            ("bedrock/acme.gpt-4-clone", (30.0, 60.0)),
+            ("bedrock/acme.claude-like", (15.0, 75.0)),
+            ("bedrock/acme.model", (1.0, 2.0)),
+            ("someprovider/gpt-3.5-turbo", (0.5, 1.5)),
+            ("gpt-4-unknown", (30.0, 60.0)),
+            ("gpt-3-unknown", (0.5, 1.5)),
+            ("claude-unknown", (15.0, 75.0)),
+            ("text-embedding-unknown", (0.1, 0.0)),
+            ("unknown-model", (1.0, 2.0)),
+        ];
+        for (model, expected) in cases {
+            assert_eq!(default_pricing_for_model(model), expected, "{}", model);
+        }
+    }
+
+    #[test]
+    fn test_context_window_split_limits() {
+        let p = pricing(serde_json::json!({
+            "max_input_tokens": 128000,
+            "max_output_tokens": 16384,
+            "max_tokens": 16384
+        }));
+        assert_eq!(p.context_window(), Some(144384));
+    }
+
+    #[test]
+    fn test_context_window_max_tokens_only() {
+        let p = pricing(serde_json::json!({ "max_tokens": 4097 }));
+        assert_eq!(p.context_window(), Some(4097));
+    }
+
+    #[test]
+    fn test_context_window_partial_or_missing() {
+        let p = pricing(serde_json::json!({ "max_input_tokens": 8192, "max_tokens": 4096 }));
+        assert_eq!(p.context_window(), Some(8192));
+
+        let p = pricing(serde_json::json!({ "input_cost_per_token": 0.000001 }));
+        assert_eq!(p.context_window(), None);
+    }
+}
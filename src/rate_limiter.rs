@@ -1,8 +1,25 @@
+use dashmap::mapref::one::{Ref, RefMut};
 use dashmap::DashMap;
 /// Rate limiting functionality
-use std::collections::HashMap;
-use std::sync::atomic::{AtomicU64, Ordering};
-use std::time::{SystemTime, UNIX_EPOCH};
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashMap};
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+/// Stale windows a counter may accumulate before they are pruned, so
+/// counters with very large limits (e.g. micro-dollar budgets) stay small
+const MAX_RETAINED_WINDOWS: usize = 64;
+
+/// Group/spend counters that may exist before expired ones are swept
+const WINDOW_SWEEP_THRESHOLD: usize = 10_000;
+
+/// Default share of a limit at which usage is reported as "warning"/"critical"
+const DEFAULT_WARNING_LEVEL: f64 = 0.8;
+const DEFAULT_CRITICAL_LEVEL: f64 = 0.95;
+
+/// Cost windows count micro-dollars so they fit the integer window counters
+const MICROS_PER_USD: f64 = 1_000_000.0;
 
 #[derive(Debug, Clone)]
 pub struct RateLimitConfig {
@@ -93,6 +110,16 @@ impl TokenBucket {
         }
     }
 
+    /// Give back tokens taken by `try_consume`, e.g. when a later check
+    /// rejects the request (never above capacity)
+    pub fn refund(&self, tokens: u64) {
+        let _ = self
+            .tokens
+            .fetch_update(Ordering::AcqRel, Ordering::Acquire, |current| {
+                Some(current.saturating_add(tokens).min(self.capacity))
+            });
+    }
+
     pub fn available_tokens(&self) -> u64 {
         let now = SystemTime::now()
             .duration_since(UNIX_EPOCH)
@@ -110,6 +137,28 @@ impl TokenBucket {
             current_tokens
         }
     }
+
+    /// Milliseconds until `cost` tokens are available (0 if they are now,
+    /// `u64::MAX` if they never will be)
+    pub fn time_until_available(&self, cost: u64) -> u64 {
+        let available = self.available_tokens();
+        if available >= cost {
+            return 0;
+        }
+        if cost > self.capacity || self.refill_rate == 0 {
+            return u64::MAX;
+        }
+
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_millis() as u64;
+        let time_passed = now.saturating_sub(self.last_refill.load(Ordering::Relaxed));
+
+        // Refills happen in whole seconds since the last refill
+        let refills = (cost - available + self.refill_rate - 1) / self.refill_rate;
+        (refills - 1) * 1000 + (1000 - time_passed % 1000)
+    }
 }
 
 #[derive(Debug)]
@@ -117,6 +166,8 @@ pub struct SlidingWindowCounter {
     windows: DashMap<u64, AtomicU64>, // timestamp_window -> count
     window_size_ms: u64,
     limit: u64,
+    /// Time of the latest recorded request (creation time until then)
+    last_active_ms: AtomicU64,
 }
 
 impl SlidingWindowCounter {
@@ -125,10 +176,16 @@ impl SlidingWindowCounter {
             windows: DashMap::new(),
             window_size_ms,
             limit,
+            last_active_ms: AtomicU64::new(now_ms()),
         }
     }
 
     pub fn try_increment(&self) -> bool {
+        self.try_increment_by(1)
+    }
+
+    /// Add `amount` to the current window if the total stays within the limit
+    pub fn try_increment_by(&self, amount: u64) -> bool {
         let now = SystemTime::now()
             .duration_since(UNIX_EPOCH)
             .unwrap_or_default()
@@ -139,18 +196,38 @@ impl SlidingWindowCounter {
         let current_window = now / self.window_size_ms;
         let current_count = self.get_current_count(current_window);
 
-        if current_count < self.limit {
+        if current_count.saturating_add(amount) <= self.limit {
             let window_counter = self
                 .windows
                 .entry(current_window)
                 .or_insert_with(|| AtomicU64::new(0));
-            window_counter.fetch_add(1, Ordering::Relaxed);
+            window_counter.fetch_add(amount, Ordering::Relaxed);
+            self.last_active_ms.fetch_max(now, Ordering::Relaxed);
             true
         } else {
             false
         }
     }
 
+    /// Take back `amount` added by `try_increment_by`, from the current
+    /// window first and then the previous one
+    pub fn refund(&self, amount: u64) {
+        let current_window = now_ms() / self.window_size_ms;
+        let mut remaining = amount;
+        for window in [current_window, current_window.saturating_sub(1)] {
+            if remaining == 0 {
+                break;
+            }
+            if let Some(counter) = self.windows.get(&window) {
+                let _ = counter.fetch_update(Ordering::Relaxed, Ordering::Relaxed, |count| {
+                    let taken = count.min(remaining);
+                    remaining -= taken;
+                    Some(count - taken)
+                });
+            }
+        }
+    }
+
     fn get_current_count(&self, current_window: u64) -> u64 {
         let mut total = 0;
 
@@ -170,7 +247,10 @@ impl SlidingWindowCounter {
         let cutoff_window = current_window.saturating_sub(1);
 
         // Only clean if we have accumulated many windows (prevent excessive cleanup)
-        if self.windows.len() > self.limit as usize * 2 {
+        let threshold = (self.limit as usize)
+            .saturating_mul(2)
+            .min(MAX_RETAINED_WINDOWS);
+        if self.windows.len() > threshold {
             self.windows.retain(|&window, _| window > cutoff_window);
         }
     }
@@ -186,13 +266,225 @@ impl SlidingWindowCounter {
 
         self.limit.saturating_sub(current_count)
     }
+
+    /// Milliseconds elapsed since the current window started
+    pub fn window_age_ms(&self) -> u64 {
+        now_ms() % self.window_size_ms
+    }
+
+    /// Drop every window older than the previous one, regardless of how
+    /// many have accumulated
+    pub fn prune(&self) {
+        let cutoff_window = (now_ms() / self.window_size_ms).saturating_sub(1);
+        self.windows.retain(|&window, _| window >= cutoff_window);
+    }
+
+    /// Milliseconds until the window admits `cost` more (0 if it does now,
+    /// `u64::MAX` if it never will)
+    pub fn time_until_available(&self, cost: u64) -> u64 {
+        if cost > self.limit {
+            return u64::MAX;
+        }
+        self.time_until_below(self.limit - cost + 1)
+    }
+
+    /// Milliseconds until the window count drops below `limit` (0 if it is now)
+    pub fn time_until_below(&self, limit: u64) -> u64 {
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_millis() as u64;
+
+        let current_window = now / self.window_size_ms;
+        if self.get_current_count(current_window) < limit {
+            return 0;
+        }
+
+        // At the next boundary the previous window drops out; if the current
+        // window alone is still at the limit we have to wait one more window.
+        let current_only = self
+            .windows
+            .get(&current_window)
+            .map(|w| w.load(Ordering::Relaxed))
+            .unwrap_or(0);
+        let windows_to_wait = if current_only < limit { 1 } else { 2 };
+
+        (current_window + windows_to_wait) * self.window_size_ms - now
+    }
+
+    /// Requests counted in the current sliding window
+    pub fn current_count(&self) -> u64 {
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_millis() as u64;
+
+        self.get_current_count(now / self.window_size_ms)
+    }
+
+    /// Record `amount` requests in the current window without checking the limit
+    pub fn record(&self, amount: u64) {
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_millis() as u64;
+
+        self.cleanup_old_windows(now);
+        self.record_at(now, amount);
+    }
+
+    /// Record `amount` requests in the window containing `timestamp_ms`
+    ///
+    /// Timestamps older than the previous window are ignored since they no
+    /// longer affect the count.
+    pub fn record_at(&self, timestamp_ms: u64, amount: u64) {
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_millis() as u64;
+
+        let window = timestamp_ms.min(now) / self.window_size_ms;
+        if window + 1 < now / self.window_size_ms {
+            return;
+        }
+
+        self.windows
+            .entry(window)
+            .or_insert_with(|| AtomicU64::new(0))
+            .fetch_add(amount, Ordering::Relaxed);
+        self.last_active_ms
+            .fetch_max(timestamp_ms.min(now), Ordering::Relaxed);
+    }
+
+    /// True once the counter has been empty for a whole window length
+    ///
+    /// A window still counts for one more window after it closes, so this
+    /// is the start of the third window after the last recorded request.
+    pub fn is_expired(&self) -> bool {
+        let last_window = self.last_active_ms.load(Ordering::Relaxed) / self.window_size_ms;
+        now_ms() / self.window_size_ms >= last_window + 3
+    }
+}
+
+/// Group or spend counters, keyed by "key:window_ms"
+///
+/// These are not tied to a key's config and outlive its eviction, so they
+/// are bounded on their own: once `sweep_at` counters exist, expired ones
+/// are dropped before another is added. If most are still live, the next
+/// sweep waits until the map has doubled, so sweeps stay amortized.
+#[derive(Debug)]
+struct WindowCounters {
+    counters: DashMap<String, SlidingWindowCounter>,
+    sweep_at: AtomicUsize,
+}
+
+impl WindowCounters {
+    fn new() -> Self {
+        Self {
+            counters: DashMap::new(),
+            sweep_at: AtomicUsize::new(WINDOW_SWEEP_THRESHOLD),
+        }
+    }
+
+    fn get(&self, key: &str, window_ms: u64) -> Option<Ref<'_, String, SlidingWindowCounter>> {
+        self.counters.get(&format!("{}:{}", key, window_ms))
+    }
+
+    /// The counter for `key` and `window_ms`, created with `limit` if missing
+    fn get_or_insert(
+        &self,
+        key: &str,
+        window_ms: u64,
+        limit: u64,
+    ) -> RefMut<'_, String, SlidingWindowCounter> {
+        let counter_key = format!("{}:{}", key, window_ms);
+        if !self.counters.contains_key(&counter_key)
+            && self.counters.len() >= self.sweep_at.load(Ordering::Relaxed)
+        {
+            self.sweep();
+        }
+        self.counters
+            .entry(counter_key)
+            .or_insert_with(|| SlidingWindowCounter::new(window_ms, limit))
+    }
+
+    fn sweep(&self) {
+        self.counters.retain(|_, counter| !counter.is_expired());
+        self.sweep_at.store(
+            WINDOW_SWEEP_THRESHOLD.max(self.counters.len() * 2),
+            Ordering::Relaxed,
+        );
+    }
+}
+
+/// Observed queueing for blocking rate limit checks
+#[derive(Debug, Default)]
+pub struct WaitStats {
+    pub waited_requests: AtomicU64,
+    pub total_wait_ms: AtomicU64,
+    pub max_wait_ms: AtomicU64,
+    pub timeouts: AtomicU64,
+    /// Times a blocking check went to sleep; about one per waited request
+    pub sleeps: AtomicU64,
+}
+
+impl WaitStats {
+    pub fn record(&self, wait_ms: u64, sleeps: u64, admitted: bool) {
+        self.sleeps.fetch_add(sleeps, Ordering::Relaxed);
+        if wait_ms > 0 {
+            self.waited_requests.fetch_add(1, Ordering::Relaxed);
+            self.total_wait_ms.fetch_add(wait_ms, Ordering::Relaxed);
+            self.max_wait_ms.fetch_max(wait_ms, Ordering::Relaxed);
+        }
+        if !admitted {
+            self.timeouts.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    pub fn to_json(&self) -> serde_json::Value {
+        let waited = self.waited_requests.load(Ordering::Relaxed);
+        let total = self.total_wait_ms.load(Ordering::Relaxed);
+        let average = if waited > 0 {
+            total as f64 / waited as f64
+        } else {
+            0.0
+        };
+
+        serde_json::json!({
+            "waited_requests": waited,
+            "total_wait_ms": total,
+            "average_wait_ms": average,
+            "max_wait_ms": self.max_wait_ms.load(Ordering::Relaxed),
+            "timeouts": self.timeouts.load(Ordering::Relaxed),
+            "sleeps": self.sleeps.load(Ordering::Relaxed)
+        })
+    }
 }
 
 pub struct RateLimiter {
     token_buckets: DashMap<String, TokenBucket>,
     minute_counters: DashMap<String, SlidingWindowCounter>,
     hour_counters: DashMap<String, SlidingWindowCounter>,
+    /// Admitted requests per minute, tracked apart from the cost-weighted windows
+    minute_requests: DashMap<String, SlidingWindowCounter>,
     configs: DashMap<String, RateLimitConfig>,
+    wait_stats: DashMap<String, WaitStats>,
+    /// Last request time for keys created on demand (the eviction candidates)
+    dynamic_keys: DashMap<String, AtomicU64>,
+    /// Dynamic keys by the request time they were queued with, oldest first;
+    /// entries go stale as keys are used and are requeued on eviction
+    eviction_queue: Mutex<BinaryHeap<Reverse<(u64, String)>>>,
+    /// Cap on dynamically created keys (0 = unlimited)
+    max_keys: AtomicUsize,
+    evictions: AtomicU64,
+    /// "key:window_ms" -> counter for hierarchical group limits
+    group_counters: WindowCounters,
+    /// Spend windows in micro-dollars, keyed by "key:window_ms"
+    cost_counters: WindowCounters,
+    /// (warning, critical) usage fractions for `check_rate_limit_with_level`
+    level_thresholds: Mutex<(f64, f64)>,
+    /// Serializes group checks so all levels are admitted or none are
+    group_lock: Mutex<()>,
 }
 
 impl Default for RateLimiter {
@@ -207,11 +499,33 @@ impl RateLimiter {
             token_buckets: DashMap::new(),
             minute_counters: DashMap::new(),
             hour_counters: DashMap::new(),
+            minute_requests: DashMap::new(),
             configs: DashMap::new(),
+            wait_stats: DashMap::new(),
+            dynamic_keys: DashMap::new(),
+            eviction_queue: Mutex::new(BinaryHeap::new()),
+            max_keys: AtomicUsize::new(0),
+            evictions: AtomicU64::new(0),
+            group_counters: WindowCounters::new(),
+            cost_counters: WindowCounters::new(),
+            level_thresholds: Mutex::new((DEFAULT_WARNING_LEVEL, DEFAULT_CRITICAL_LEVEL)),
+            group_lock: Mutex::new(()),
         }
     }
 
+    /// Cap the number of keys created on demand by `check_rate_limit`
+    ///
+    /// Once the cap is reached, the key whose most recent request is oldest is
+    /// evicted before a new key is tracked. Keys configured explicitly through
+    /// `set_config` are never evicted. 0 disables the cap.
+    pub fn set_max_keys(&self, max_keys: usize) {
+        self.max_keys.store(max_keys, Ordering::Relaxed);
+    }
+
     pub fn set_config(&self, key: &str, config: RateLimitConfig) {
+        // Explicitly configured keys are no longer eviction candidates
+        self.dynamic_keys.remove(key);
+
         // Create token bucket for burst control
         let bucket = TokenBucket::new(config.burst_size, config.requests_per_second);
         self.token_buckets.insert(key.to_string(), bucket);
@@ -222,34 +536,67 @@ impl RateLimiter {
 
         self.minute_counters.insert(key.to_string(), minute_counter);
         self.hour_counters.insert(key.to_string(), hour_counter);
+        self.minute_requests.insert(
+            key.to_string(),
+            SlidingWindowCounter::new(60000, config.requests_per_minute),
+        );
         self.configs.insert(key.to_string(), config);
     }
 
     pub fn check_rate_limit(&self, key: &str) -> RateLimitResult {
+        self.check_rate_limit_with_cost(key, 1)
+    }
+
+    /// Check a request that counts `cost` units against the key's limits
+    ///
+    /// Lets heavier operations (image generation, large embedding batches)
+    /// consume more of a quota than a single request. A cost of 1 is the same
+    /// as `check_rate_limit`. A cost larger than the burst size or a window
+    /// limit can never be admitted and is rejected without a `retry_after_ms`.
+    /// If a later limit rejects the request, what earlier ones took is
+    /// given back.
+    pub fn check_rate_limit_with_cost(&self, key: &str, cost: u64) -> RateLimitResult {
         // Ensure config exists
         if !self.configs.contains_key(key) {
-            self.set_config(key, RateLimitConfig::default());
+            self.create_dynamic_key(key);
+        }
+        if let Some(last_request) = self.dynamic_keys.get(key) {
+            last_request.store(now_ms(), Ordering::Relaxed);
+        }
+
+        if let Some(rejection) = self.check_cost_fits(key, cost) {
+            return rejection;
         }
 
         // Check token bucket (for burst and per-second limits)
-        if let Some(bucket) = self.token_buckets.get(key) {
-            if !bucket.try_consume(1) {
+        let bucket = self.token_buckets.get(key);
+        if let Some(bucket) = &bucket {
+            if !bucket.try_consume(cost) {
                 return RateLimitResult {
                     allowed: false,
+                    kind: AdmissionKind::RateLimited,
                     reason: "Rate limit exceeded (requests per second)".to_string(),
-                    retry_after_ms: Some(1000),
+                    retry_after_ms: Some(bucket.time_until_available(cost)),
                     remaining_requests: bucket.available_tokens(),
                 };
             }
         }
+        let refund_bucket = || {
+            if let Some(bucket) = &bucket {
+                bucket.refund(cost);
+            }
+        };
 
         // Check minute limit
-        if let Some(minute_counter) = self.minute_counters.get(key) {
-            if !minute_counter.try_increment() {
+        let minute_counter = self.minute_counters.get(key);
+        if let Some(minute_counter) = &minute_counter {
+            if !minute_counter.try_increment_by(cost) {
+                refund_bucket();
                 return RateLimitResult {
                     allowed: false,
+                    kind: AdmissionKind::RateLimited,
                     reason: "Rate limit exceeded (requests per minute)".to_string(),
-                    retry_after_ms: Some(60000),
+                    retry_after_ms: Some(minute_counter.time_until_available(cost)),
                     remaining_requests: minute_counter.get_remaining(),
                 };
             }
@@ -257,24 +604,409 @@ impl RateLimiter {
 
         // Check hour limit
         if let Some(hour_counter) = self.hour_counters.get(key) {
-            if !hour_counter.try_increment() {
+            if !hour_counter.try_increment_by(cost) {
+                refund_bucket();
+                if let Some(minute_counter) = &minute_counter {
+                    minute_counter.refund(cost);
+                }
                 return RateLimitResult {
                     allowed: false,
+                    kind: AdmissionKind::RateLimited,
                     reason: "Rate limit exceeded (requests per hour)".to_string(),
-                    retry_after_ms: Some(3600000),
+                    retry_after_ms: Some(hour_counter.time_until_available(cost)),
                     remaining_requests: hour_counter.get_remaining(),
                 };
             }
         }
+        drop(bucket);
+        drop(minute_counter);
+
+        if let Some(requests) = self.minute_requests.get(key) {
+            requests.record(1);
+        }
 
         RateLimitResult {
             allowed: true,
+            kind: AdmissionKind::Allowed,
             reason: "Request allowed".to_string(),
             retry_after_ms: None,
             remaining_requests: self.get_remaining_requests(key),
         }
     }
 
+    /// Rejection for a `cost` that exceeds one of the key's limits outright
+    fn check_cost_fits(&self, key: &str, cost: u64) -> Option<RateLimitResult> {
+        let config = self.configs.get(key)?;
+        let (limit, name) = [
+            (config.burst_size, "burst size"),
+            (config.requests_per_minute, "per-minute limit"),
+            (config.requests_per_hour, "per-hour limit"),
+        ]
+        .into_iter()
+        .find(|(limit, _)| cost > *limit)?;
+
+        Some(RateLimitResult {
+            allowed: false,
+            kind: AdmissionKind::RateLimited,
+            reason: format!("Request cost {} exceeds the {} of {}", cost, name, limit),
+            retry_after_ms: None,
+            remaining_requests: 0,
+        })
+    }
+
+    fn create_dynamic_key(&self, key: &str) {
+        let max_keys = self.max_keys.load(Ordering::Relaxed);
+        while max_keys > 0 && self.dynamic_keys.len() >= max_keys {
+            if !self.evict_least_recent() {
+                break;
+            }
+        }
+
+        self.set_config(key, RateLimitConfig::default());
+        let now = now_ms();
+        self.dynamic_keys
+            .insert(key.to_string(), AtomicU64::new(now));
+        self.eviction_queue
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .push(Reverse((now, key.to_string())));
+    }
+
+    /// Evict the dynamic key with the oldest most-recent request
+    ///
+    /// Keys used since they were queued are requeued at their latest
+    /// request time rather than evicted, so each eviction costs O(log n)
+    /// amortized.
+    fn evict_least_recent(&self) -> bool {
+        let mut queue = self
+            .eviction_queue
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+
+        while let Some(Reverse((queued_ms, key))) = queue.pop() {
+            let last_request = match self.dynamic_keys.get(&key) {
+                Some(last_request) => last_request.load(Ordering::Relaxed),
+                // Configured explicitly since it was queued
+                None => continue,
+            };
+            if last_request > queued_ms {
+                queue.push(Reverse((last_request, key)));
+                continue;
+            }
+
+            self.remove_key(&key);
+            self.evictions.fetch_add(1, Ordering::Relaxed);
+            return true;
+        }
+        false
+    }
+
+    /// Forget a dynamic key's config and per-key windows
+    ///
+    /// Group and spend windows are not per-key state (a spend window must
+    /// survive its key being evicted) and expire on their own.
+    fn remove_key(&self, key: &str) {
+        self.dynamic_keys.remove(key);
+        self.token_buckets.remove(key);
+        self.minute_counters.remove(key);
+        self.hour_counters.remove(key);
+        self.minute_requests.remove(key);
+        self.configs.remove(key);
+        self.wait_stats.remove(key);
+    }
+
+    /// Limiter-wide summary: tracked keys, key cap and evictions
+    pub fn get_summary(&self) -> HashMap<String, serde_json::Value> {
+        let mut summary = HashMap::new();
+        summary.insert(
+            "tracked_keys".to_string(),
+            serde_json::Value::Number(serde_json::Number::from(self.configs.len())),
+        );
+        summary.insert(
+            "dynamic_keys".to_string(),
+            serde_json::Value::Number(serde_json::Number::from(self.dynamic_keys.len())),
+        );
+        summary.insert(
+            "max_keys".to_string(),
+            serde_json::Value::Number(serde_json::Number::from(
+                self.max_keys.load(Ordering::Relaxed),
+            )),
+        );
+        summary.insert(
+            "evictions".to_string(),
+            serde_json::Value::Number(serde_json::Number::from(
+                self.evictions.load(Ordering::Relaxed),
+            )),
+        );
+        summary
+    }
+
+    /// Backfill a key's windows from replayed usage
+    ///
+    /// Each entry is `(age_seconds_ago, amount)` and is recorded in the minute
+    /// and hour windows it falls into, so limits after a restart reflect
+    /// recent real usage. The per-second token bucket is not backfilled since
+    /// it refills within a second anyway.
+    pub fn consume_batch(&self, key: &str, entries: &[(f64, u64)]) {
+        if !self.configs.contains_key(key) {
+            self.create_dynamic_key(key);
+        }
+
+        let now = now_ms();
+        for &(age_seconds, amount) in entries {
+            let age_ms = (age_seconds.max(0.0) * 1000.0) as u64;
+            let timestamp = now.saturating_sub(age_ms);
+
+            if let Some(counter) = self.minute_counters.get(key) {
+                counter.record_at(timestamp, amount);
+            }
+            if let Some(counter) = self.hour_counters.get(key) {
+                counter.record_at(timestamp, amount);
+            }
+        }
+    }
+
+    /// Check a hierarchy of limits (e.g. org -> project -> key) at once
+    ///
+    /// Each level is `(key, limit, window_seconds)`. The request is admitted
+    /// only if every level has capacity, in which case it is recorded in all
+    /// of them; on rejection nothing is recorded. Checks and records happen
+    /// under a single lock so concurrent group checks can't interleave.
+    pub fn check_rate_limit_group(&self, levels: &[(String, u64, u64)]) -> RateLimitResult {
+        let _guard = self
+            .group_lock
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+
+        let mut remaining = u64::MAX;
+        for (key, limit, window_seconds) in levels {
+            let window_ms = window_seconds.saturating_mul(1000).max(1);
+            let counter = self.group_counters.get_or_insert(key, window_ms, *limit);
+
+            let count = counter.current_count();
+            if count >= *limit {
+                return RateLimitResult {
+                    allowed: false,
+                    kind: AdmissionKind::RateLimited,
+                    reason: format!("Rate limit exceeded for group level '{}'", key),
+                    retry_after_ms: Some(counter.time_until_below(*limit)),
+                    remaining_requests: 0,
+                };
+            }
+            remaining = remaining.min(limit - count - 1);
+        }
+
+        for (key, _, window_seconds) in levels {
+            let window_ms = window_seconds.saturating_mul(1000).max(1);
+            if let Some(counter) = self.group_counters.get(key, window_ms) {
+                counter.record(1);
+            }
+        }
+
+        RateLimitResult {
+            allowed: true,
+            kind: AdmissionKind::Allowed,
+            reason: "Request allowed".to_string(),
+            retry_after_ms: None,
+            remaining_requests: if levels.is_empty() { 0 } else { remaining },
+        }
+    }
+
+    /// Set the usage fractions at which `check_rate_limit_with_level`
+    /// reports "warning" and "critical"
+    pub fn set_level_thresholds(&self, warning: f64, critical: f64) {
+        let warning = warning.clamp(0.0, 1.0);
+        *self
+            .level_thresholds
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner()) =
+            (warning, critical.clamp(warning, 1.0));
+    }
+
+    /// Check a single `limit` per `window_seconds` and report how close the
+    /// key is to it
+    ///
+    /// Shares windows with `check_rate_limit_group`. Returns the result, the
+    /// count in the window after this request and a level of "ok", "warning"
+    /// or "critical" from the configured thresholds.
+    pub fn check_rate_limit_with_level(
+        &self,
+        key: &str,
+        limit: u64,
+        window_seconds: u64,
+    ) -> (RateLimitResult, u64, &'static str) {
+        let result = self.check_rate_limit_group(&[(key.to_string(), limit, window_seconds)]);
+
+        let window_ms = window_seconds.saturating_mul(1000).max(1);
+        let count = self
+            .group_counters
+            .get(key, window_ms)
+            .map(|counter| counter.current_count())
+            .unwrap_or(0);
+
+        let (warning, critical) = *self
+            .level_thresholds
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        let usage = if limit == 0 {
+            1.0
+        } else {
+            count as f64 / limit as f64
+        };
+        let level = if !result.allowed || usage >= critical {
+            "critical"
+        } else if usage >= warning {
+            "warning"
+        } else {
+            "ok"
+        };
+
+        (result, count, level)
+    }
+
+    /// Cap estimated spend per key over a sliding window
+    ///
+    /// The request's cost is estimated from the pricing data and admitted
+    /// only if the spend already in the window plus this request stays within
+    /// `limit_usd`; admitted spend is recorded. `remaining_requests` reports
+    /// how many more requests of the same cost would fit. Models without
+    /// pricing data are denied, since their spend can't be accounted for.
+    pub fn check_cost_limit(
+        &self,
+        key: &str,
+        model: &str,
+        est_input_tokens: usize,
+        est_output_tokens: usize,
+        limit_usd: f64,
+        window_seconds: u64,
+    ) -> RateLimitResult {
+        let pricing = crate::pricing::get_pricing_data();
+        let priced = pricing.get_input_cost_per_1m(model).is_some()
+            && pricing.get_output_cost_per_1m(model).is_some();
+        let cost_usd =
+            match crate::tokens::estimate_cost(est_input_tokens, est_output_tokens, model) {
+                Ok(cost_usd) if priced => cost_usd,
+                _ => {
+                    return RateLimitResult {
+                        allowed: false,
+                        kind: AdmissionKind::UnpricedModel,
+                        reason: format!("No pricing data for model '{}'", model),
+                        retry_after_ms: None,
+                        remaining_requests: 0,
+                    }
+                }
+            };
+        self.check_cost_micros(
+            key,
+            (cost_usd * MICROS_PER_USD).ceil() as u64,
+            limit_usd,
+            window_seconds,
+        )
+    }
+
+    /// Admit and record `cost` micro-dollars against the key's spend window
+    fn check_cost_micros(
+        &self,
+        key: &str,
+        cost: u64,
+        limit_usd: f64,
+        window_seconds: u64,
+    ) -> RateLimitResult {
+        let limit = (limit_usd.max(0.0) * MICROS_PER_USD) as u64;
+        let window_ms = window_seconds.saturating_mul(1000).max(1);
+
+        // Holding the entry keeps check and record atomic per key and window
+        let counter = self.cost_counters.get_or_insert(key, window_ms, limit);
+
+        let spent = counter.current_count();
+        if spent.saturating_add(cost) > limit {
+            return RateLimitResult {
+                allowed: false,
+                kind: AdmissionKind::OverBudget,
+                reason: format!(
+                    "Cost limit exceeded (${:.6} spent of ${:.6} per {}s)",
+                    spent as f64 / MICROS_PER_USD,
+                    limit_usd,
+                    window_seconds
+                ),
+                retry_after_ms: (cost <= limit).then(|| counter.time_until_below(limit - cost + 1)),
+                remaining_requests: 0,
+            };
+        }
+
+        counter.record(cost);
+        RateLimitResult {
+            allowed: true,
+            kind: AdmissionKind::Allowed,
+            reason: "Request allowed".to_string(),
+            retry_after_ms: None,
+            remaining_requests: (limit - spent - cost).checked_div(cost).unwrap_or(u64::MAX),
+        }
+    }
+
+    /// Check rate limit, blocking up to `max_wait_ms` for a slot to free up
+    ///
+    /// Sleeps for exactly the time until the limiting bucket/window admits
+    /// `cost` instead of polling. The observed wait is recorded per key.
+    pub fn check_rate_limit_blocking(
+        &self,
+        key: &str,
+        max_wait_ms: u64,
+        cost: u64,
+    ) -> RateLimitResult {
+        let start = Instant::now();
+        let mut sleeps = 0;
+
+        loop {
+            let result = self.check_rate_limit_with_cost(key, cost);
+            let waited_ms = start.elapsed().as_millis() as u64;
+
+            if result.allowed {
+                self.wait_stats
+                    .entry(key.to_string())
+                    .or_default()
+                    .record(waited_ms, sleeps, true);
+                return result;
+            }
+
+            let wait_ms = self.time_until_available(key, cost).max(1);
+            if waited_ms.saturating_add(wait_ms) > max_wait_ms {
+                self.wait_stats
+                    .entry(key.to_string())
+                    .or_default()
+                    .record(waited_ms, sleeps, false);
+                return result;
+            }
+
+            std::thread::sleep(Duration::from_millis(wait_ms));
+            sleeps += 1;
+        }
+    }
+
+    /// Milliseconds until `key` would admit a request of `cost` (0 if it
+    /// would now, `u64::MAX` if it never will)
+    pub fn time_until_available(&self, key: &str, cost: u64) -> u64 {
+        let bucket_wait = self
+            .token_buckets
+            .get(key)
+            .map(|b| b.time_until_available(cost))
+            .unwrap_or(0);
+
+        let minute_wait = self
+            .minute_counters
+            .get(key)
+            .map(|c| c.time_until_available(cost))
+            .unwrap_or(0);
+
+        let hour_wait = self
+            .hour_counters
+            .get(key)
+            .map(|c| c.time_until_available(cost))
+            .unwrap_or(0);
+
+        bucket_wait.max(minute_wait).max(hour_wait)
+    }
+
     pub fn get_remaining_requests(&self, key: &str) -> u64 {
         let bucket_remaining = self
             .token_buckets
@@ -300,6 +1032,51 @@ impl RateLimiter {
         )
     }
 
+    /// Current usage for a single key, or None if the key isn't tracked
+    ///
+    /// `tokens_in_window` is the cost-weighted minute usage (what callers
+    /// pass as `cost`, typically tokens); `requests_in_window` counts
+    /// admitted requests. Stale windows are pruned first.
+    pub fn get_key_stats(&self, key: &str) -> Option<HashMap<String, serde_json::Value>> {
+        let minute_counter = self.minute_counters.get(key)?;
+        minute_counter.prune();
+        let requests_in_window = self
+            .minute_requests
+            .get(key)
+            .map(|c| {
+                c.prune();
+                c.current_count()
+            })
+            .unwrap_or(0);
+        let hour_tokens_in_window = self
+            .hour_counters
+            .get(key)
+            .map(|c| {
+                c.prune();
+                c.current_count()
+            })
+            .unwrap_or(0);
+
+        let mut stats = HashMap::new();
+        stats.insert(
+            "requests_in_window".to_string(),
+            serde_json::json!(requests_in_window),
+        );
+        stats.insert(
+            "tokens_in_window".to_string(),
+            serde_json::json!(minute_counter.current_count()),
+        );
+        stats.insert(
+            "hour_tokens_in_window".to_string(),
+            serde_json::json!(hour_tokens_in_window),
+        );
+        stats.insert(
+            "window_reset_ms".to_string(),
+            serde_json::json!(minute_counter.window_size_ms - minute_counter.window_age_ms()),
+        );
+        Some(stats)
+    }
+
     pub fn get_stats(&self) -> HashMap<String, serde_json::Value> {
         let mut stats = HashMap::new();
 
@@ -308,6 +1085,11 @@ impl RateLimiter {
             let config = entry.value();
 
             let remaining = self.get_remaining_requests(key);
+            let queue_wait = self
+                .wait_stats
+                .get(key)
+                .map(|w| w.to_json())
+                .unwrap_or_else(|| WaitStats::default().to_json());
 
             let key_stats = serde_json::json!({
                 "config": {
@@ -319,7 +1101,12 @@ impl RateLimiter {
                 "remaining_requests": remaining,
                 "bucket_tokens": self.token_buckets.get(key).map(|b| b.available_tokens()).unwrap_or(0),
                 "minute_remaining": self.minute_counters.get(key).map(|c| c.get_remaining()).unwrap_or(0),
-                "hour_remaining": self.hour_counters.get(key).map(|c| c.get_remaining()).unwrap_or(0)
+                "hour_remaining": self.hour_counters.get(key).map(|c| c.get_remaining()).unwrap_or(0),
+                "minute_requests": self.minute_requests.get(key).map(|c| c.current_count()).unwrap_or(0),
+                "minute_cost": self.minute_counters.get(key).map(|c| c.current_count()).unwrap_or(0),
+                "minute_window_age_ms": self.minute_counters.get(key).map(|c| c.window_age_ms()).unwrap_or(0),
+                "hour_window_age_ms": self.hour_counters.get(key).map(|c| c.window_age_ms()).unwrap_or(0),
+                "queue_wait": queue_wait
             });
 
             stats.insert(key.clone(), key_stats);
@@ -329,9 +1116,50 @@ impl RateLimiter {
     }
 }
 
+fn now_ms() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_millis() as u64
+}
+
+/// Why a request was or wasn't admitted, for mapping onto a response status
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AdmissionKind {
+    Allowed,
+    RateLimited,
+    OverBudget,
+    NoCapacity,
+    UnpricedModel,
+}
+
+impl AdmissionKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            AdmissionKind::Allowed => "allowed",
+            AdmissionKind::RateLimited => "rate_limited",
+            AdmissionKind::OverBudget => "over_budget",
+            AdmissionKind::NoCapacity => "no_capacity",
+            AdmissionKind::UnpricedModel => "unpriced_model",
+        }
+    }
+
+    /// HTTP status a gateway would answer with
+    pub fn http_status(&self) -> u16 {
+        match self {
+            AdmissionKind::Allowed => 200,
+            AdmissionKind::RateLimited => 429,
+            AdmissionKind::OverBudget => 402,
+            AdmissionKind::NoCapacity => 503,
+            AdmissionKind::UnpricedModel => 422,
+        }
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct RateLimitResult {
     pub allowed: bool,
+    pub kind: AdmissionKind,
     pub reason: String,
     pub retry_after_ms: Option<u64>,
     pub remaining_requests: u64,
@@ -346,10 +1174,64 @@ pub fn check_rate_limit(key: &str) -> RateLimitResult {
     RATE_LIMITER.check_rate_limit(key)
 }
 
+pub fn check_rate_limit_with_cost(key: &str, cost: u64) -> RateLimitResult {
+    RATE_LIMITER.check_rate_limit_with_cost(key, cost)
+}
+
+pub fn check_rate_limit_blocking(key: &str, max_wait_ms: u64, cost: u64) -> RateLimitResult {
+    RATE_LIMITER.check_rate_limit_blocking(key, max_wait_ms, cost)
+}
+
+pub fn consume_batch(key: &str, entries: &[(f64, u64)]) {
+    RATE_LIMITER.consume_batch(key, entries);
+}
+
+pub fn set_level_thresholds(warning: f64, critical: f64) {
+    RATE_LIMITER.set_level_thresholds(warning, critical);
+}
+
+pub fn check_rate_limit_with_level(
+    key: &str,
+    limit: u64,
+    window_seconds: u64,
+) -> (RateLimitResult, u64, &'static str) {
+    RATE_LIMITER.check_rate_limit_with_level(key, limit, window_seconds)
+}
+
+pub fn check_cost_limit(
+    key: &str,
+    model: &str,
+    est_input_tokens: usize,
+    est_output_tokens: usize,
+    limit_usd: f64,
+    window_seconds: u64,
+) -> RateLimitResult {
+    RATE_LIMITER.check_cost_limit(
+        key,
+        model,
+        est_input_tokens,
+        est_output_tokens,
+        limit_usd,
+        window_seconds,
+    )
+}
+
+pub fn check_rate_limit_group(levels: &[(String, u64, u64)]) -> RateLimitResult {
+    RATE_LIMITER.check_rate_limit_group(levels)
+}
+
 pub fn set_rate_limit_config(key: &str, config: RateLimitConfig) {
     RATE_LIMITER.set_config(key, config);
 }
 
+pub fn set_max_keys(max_keys: usize) {
+    RATE_LIMITER.set_max_keys(max_keys);
+}
+
+pub fn get_rate_limit_summary() -> HashMap<String, serde_json::Value> {
+    RATE_LIMITER.get_summary()
+}
+
 pub fn get_remaining_requests(key: &str) -> u64 {
     RATE_LIMITER.get_remaining_requests(key)
 }
@@ -357,3 +1239,365 @@ pub fn get_remaining_requests(key: &str) -> u64 {
 pub fn get_rate_limit_stats() -> HashMap<String, serde_json::Value> {
     RATE_LIMITER.get_stats()
 }
+
+pub fn get_key_stats(key: &str) -> Option<HashMap<String, serde_json::Value>> {
+    RATE_LIMITER.get_key_stats(key)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn limiter_with(key: &str, burst_size: u64, requests_per_second: u64) -> RateLimiter {
+        let limiter = RateLimiter::new();
+        limiter.set_config(
+            key,
+            RateLimitConfig {
+                requests_per_second,
+                requests_per_minute: 1000,
+                requests_per_hour: 10000,
+                burst_size,
+            },
+        );
+        limiter
+    }
+
+    #[test]
+    fn test_bucket_wait_covers_the_whole_deficit() {
+        let bucket = TokenBucket::new(6, 3);
+        assert_eq!(bucket.time_until_available(6), 0);
+        assert!(bucket.try_consume(6));
+
+        // 6 tokens at 3/s takes two refills
+        let wait = bucket.time_until_available(6);
+        assert!((1000..=2000).contains(&wait), "wait {}", wait);
+        assert!(bucket.time_until_available(1) <= 1000);
+        assert_eq!(bucket.time_until_available(7), u64::MAX);
+    }
+
+    #[test]
+    fn test_blocking_with_cost_sleeps_once() {
+        let limiter = limiter_with("heavy", 6, 3);
+        assert!(limiter.check_rate_limit_with_cost("heavy", 6).allowed);
+
+        let start = Instant::now();
+        let result = limiter.check_rate_limit_blocking("heavy", 5000, 6);
+        assert!(result.allowed);
+        assert!(start.elapsed() >= Duration::from_millis(1000));
+
+        let stats = limiter.wait_stats.get("heavy").unwrap().to_json();
+        assert!(stats["sleeps"].as_u64().unwrap() <= 2, "stats {}", stats);
+        assert_eq!(stats["waited_requests"], 1);
+    }
+
+    #[test]
+    fn test_blocking_gives_up_on_unsatisfiable_cost() {
+        let limiter = limiter_with("small", 5, 5);
+        let start = Instant::now();
+        let result = limiter.check_rate_limit_blocking("small", 5000, 6);
+        assert!(!result.allowed);
+        assert!(start.elapsed() < Duration::from_millis(100));
+
+        let stats = limiter.wait_stats.get("small").unwrap().to_json();
+        assert_eq!(stats["sleeps"], 0);
+        assert_eq!(stats["timeouts"], 1);
+    }
+
+    #[test]
+    fn test_max_keys_evicts_least_recent_dynamic_key() {
+        let limiter = RateLimiter::new();
+        limiter.set_config("configured", RateLimitConfig::default());
+        limiter.set_max_keys(2);
+
+        for key in ["a", "b", "a"] {
+            assert!(limiter.check_rate_limit(key).allowed);
+            std::thread::sleep(Duration::from_millis(2));
+        }
+        // "b" is the least recently used dynamic key
+        assert!(limiter.check_rate_limit("c").allowed);
+
+        assert!(limiter.get_key_stats("b").is_none());
+        assert!(limiter.get_key_stats("a").is_some());
+        assert!(limiter.get_key_stats("configured").is_some());
+        let summary = limiter.get_summary();
+        assert_eq!(summary["tracked_keys"], 3);
+        assert_eq!(summary["dynamic_keys"], 2);
+        assert_eq!(summary["max_keys"], 2);
+        assert_eq!(summary["evictions"], 1);
+    }
+
+    #[test]
+    fn test_evicting_a_key_keeps_its_group_windows() {
+        let limiter = RateLimiter::new();
+        limiter.set_max_keys(1);
+        assert!(limiter.check_rate_limit("team:a").allowed);
+        limiter.check_rate_limit_with_level("team:a", 10, 60);
+        limiter.check_rate_limit_group(&[("org".to_string(), 10, 60)]);
+
+        std::thread::sleep(Duration::from_millis(2));
+        assert!(limiter.check_rate_limit("team:b").allowed);
+        assert!(limiter.get_key_stats("team:a").is_none());
+        assert_eq!(limiter.group_counters.counters.len(), 2);
+        let (_, count, _) = limiter.check_rate_limit_with_level("team:a", 10, 60);
+        assert_eq!(count, 2);
+    }
+
+    #[test]
+    fn test_window_expires_after_a_full_empty_window() {
+        let counter = SlidingWindowCounter::new(20, 10);
+        assert!(counter.try_increment());
+        assert!(!counter.is_expired());
+
+        // Still counted in the next window, then empty for a whole one
+        std::thread::sleep(Duration::from_millis(30));
+        assert!(!counter.is_expired());
+        std::thread::sleep(Duration::from_millis(40));
+        assert!(counter.is_expired());
+    }
+
+    #[test]
+    fn test_window_counters_sweep_expired_at_the_bound() {
+        let windows = WindowCounters::new();
+        windows.sweep_at.store(2, Ordering::Relaxed);
+        windows.get_or_insert("idle", 20, 10).record(1);
+        windows.get_or_insert("busy", 60_000, 10).record(1);
+
+        std::thread::sleep(Duration::from_millis(70));
+        windows.get_or_insert("new", 60_000, 10);
+        let mut remaining: Vec<String> = windows
+            .counters
+            .iter()
+            .map(|entry| entry.key().clone())
+            .collect();
+        remaining.sort();
+        assert_eq!(remaining, ["busy:60000", "new:60000"]);
+        // With few live counters left, the next sweep waits for the bound again
+        assert_eq!(
+            windows.sweep_at.load(Ordering::Relaxed),
+            WINDOW_SWEEP_THRESHOLD
+        );
+    }
+
+    #[test]
+    fn test_consume_batch_backfills_windows() {
+        let limiter = limiter_with("replay", 1000, 1000);
+        limiter.consume_batch(
+            "replay",
+            &[(1.0, 300), (0.0, 200), (7200.0, 500), (-5.0, 100)],
+        );
+
+        let stats = limiter.get_key_stats("replay").unwrap();
+        // The two-hour-old entry is outside both windows; negative ages count as now
+        assert_eq!(stats["tokens_in_window"], 600);
+        assert_eq!(stats["hour_tokens_in_window"], 600);
+        assert_eq!(stats["requests_in_window"], 0);
+
+        // 1000 per minute: 600 replayed leaves room for 400
+        assert!(limiter.check_rate_limit_with_cost("replay", 400).allowed);
+        assert!(!limiter.check_rate_limit_with_cost("replay", 1).allowed);
+    }
+
+    #[test]
+    fn test_cost_over_capacity_rejected_up_front() {
+        let limiter = limiter_with("big", 5, 5);
+        let result = limiter.check_rate_limit_with_cost("big", 6);
+        assert!(!result.allowed);
+        assert_eq!(result.reason, "Request cost 6 exceeds the burst size of 5");
+        assert_eq!(result.retry_after_ms, None);
+        // Nothing was taken
+        assert_eq!(limiter.get_remaining_requests("big"), 5);
+    }
+
+    #[test]
+    fn test_retry_after_reflects_the_deficit() {
+        let limiter = limiter_with("slow", 6, 3);
+        assert!(limiter.check_rate_limit_with_cost("slow", 6).allowed);
+
+        let one = limiter.check_rate_limit_with_cost("slow", 1);
+        let six = limiter.check_rate_limit_with_cost("slow", 6);
+        assert!(one.retry_after_ms.unwrap() <= 1000);
+        assert!(six.retry_after_ms.unwrap() > 1000);
+    }
+
+    #[test]
+    fn test_rejections_refund_earlier_limits() {
+        // No refill, so bucket levels only move through consume and refund
+        let limiter = RateLimiter::new();
+        limiter.set_config(
+            "minute",
+            RateLimitConfig {
+                requests_per_second: 0,
+                requests_per_minute: 5,
+                requests_per_hour: 100,
+                burst_size: 10,
+            },
+        );
+        assert!(limiter.check_rate_limit_with_cost("minute", 4).allowed);
+        let result = limiter.check_rate_limit_with_cost("minute", 3);
+        assert!(!result.allowed);
+        assert_eq!(result.reason, "Rate limit exceeded (requests per minute)");
+        assert_eq!(
+            limiter
+                .token_buckets
+                .get("minute")
+                .unwrap()
+                .available_tokens(),
+            6
+        );
+
+        limiter.set_config(
+            "hour",
+            RateLimitConfig {
+                requests_per_second: 0,
+                requests_per_minute: 50,
+                requests_per_hour: 5,
+                burst_size: 10,
+            },
+        );
+        assert!(limiter.check_rate_limit_with_cost("hour", 4).allowed);
+        let result = limiter.check_rate_limit_with_cost("hour", 3);
+        assert!(!result.allowed);
+        assert_eq!(result.reason, "Rate limit exceeded (requests per hour)");
+        assert_eq!(
+            limiter
+                .token_buckets
+                .get("hour")
+                .unwrap()
+                .available_tokens(),
+            6
+        );
+        let stats = limiter.get_key_stats("hour").unwrap();
+        assert_eq!(stats["tokens_in_window"], 4);
+        assert_eq!(stats["hour_tokens_in_window"], 4);
+        assert_eq!(stats["requests_in_window"], 1);
+
+        // What was refunded is still usable
+        assert!(limiter.check_rate_limit_with_cost("hour", 1).allowed);
+    }
+
+    #[test]
+    fn test_admission_kinds() {
+        let kinds = [
+            (AdmissionKind::Allowed, "allowed", 200),
+            (AdmissionKind::RateLimited, "rate_limited", 429),
+            (AdmissionKind::OverBudget, "over_budget", 402),
+            (AdmissionKind::NoCapacity, "no_capacity", 503),
+            (AdmissionKind::UnpricedModel, "unpriced_model", 422),
+        ];
+        for (kind, name, status) in kinds {
+            assert_eq!(kind.as_str(), name);
+            assert_eq!(kind.http_status(), status);
+        }
+
+        let limiter = limiter_with("user", 1, 0);
+        assert_eq!(
+            limiter.check_rate_limit("user").kind,
+            AdmissionKind::Allowed
+        );
+        let denied = limiter.check_rate_limit("user");
+        assert!(!denied.allowed);
+        assert_eq!(denied.kind, AdmissionKind::RateLimited);
+
+        let denied = limiter.check_cost_micros("user", 1, 0.0, 60);
+        assert!(!denied.allowed);
+        assert_eq!(denied.kind, AdmissionKind::OverBudget);
+    }
+
+    #[test]
+    fn test_cost_limit_accounts_in_micro_dollars() {
+        let limiter = RateLimiter::new();
+
+        // $0.00001 = 10 micro-dollars, three 3-micro-dollar requests fit
+        for remaining in [2, 1, 0] {
+            let result = limiter.check_cost_micros("user", 3, 0.00001, 60);
+            assert!(result.allowed);
+            assert_eq!(result.remaining_requests, remaining);
+        }
+        let denied = limiter.check_cost_micros("user", 3, 0.00001, 60);
+        assert!(!denied.allowed);
+        assert_eq!(denied.kind, AdmissionKind::OverBudget);
+        assert!(denied.retry_after_ms.is_some());
+        // The remaining micro-dollar still fits a smaller request
+        assert!(limiter.check_cost_micros("user", 1, 0.00001, 60).allowed);
+
+        // Windows of different length are tracked separately
+        assert!(limiter.check_cost_micros("user", 3, 0.00001, 3600).allowed);
+    }
+
+    #[test]
+    fn test_cost_limit_denies_unpriced_models() {
+        let limiter = RateLimiter::new();
+        let result =
+            limiter.check_cost_limit("user", "no-such-model-for-tests", 1000, 1000, 1000.0, 60);
+        assert!(!result.allowed);
+        assert_eq!(result.kind, AdmissionKind::UnpricedModel);
+        assert_eq!(result.retry_after_ms, None);
+        assert!(result.reason.contains("no-such-model-for-tests"));
+        // Nothing was recorded against the key
+        assert!(limiter.cost_counters.counters.is_empty());
+    }
+
+    #[test]
+    fn test_key_stats_and_summary() {
+        let limiter = limiter_with("user", 10, 10);
+        assert!(limiter.check_rate_limit_with_cost("user", 3).allowed);
+        assert!(limiter.check_rate_limit("user").allowed);
+
+        let stats = limiter.get_key_stats("user").unwrap();
+        assert_eq!(stats["requests_in_window"], 2);
+        assert_eq!(stats["tokens_in_window"], 4);
+        assert_eq!(stats["hour_tokens_in_window"], 4);
+        let reset_ms = stats["window_reset_ms"].as_u64().unwrap();
+        assert!(reset_ms > 0 && reset_ms <= 60_000, "{}", reset_ms);
+        assert!(limiter.get_key_stats("missing").is_none());
+
+        limiter.set_max_keys(1);
+        limiter.check_rate_limit("anon-1");
+        limiter.check_rate_limit("anon-2");
+        let summary = limiter.get_summary();
+        assert_eq!(
+            summary["tracked_keys"], 2,
+            "configured key plus one dynamic"
+        );
+        assert_eq!(summary["dynamic_keys"], 1);
+        assert_eq!(summary["max_keys"], 1);
+        assert_eq!(summary["evictions"], 1);
+    }
+
+    #[test]
+    fn test_wait_stats() {
+        let stats = WaitStats::default();
+        stats.record(0, 0, true);
+        stats.record(30, 1, true);
+        stats.record(10, 1, false);
+
+        assert_eq!(
+            stats.to_json(),
+            serde_json::json!({
+                "waited_requests": 2,
+                "total_wait_ms": 40,
+                "average_wait_ms": 20.0,
+                "max_wait_ms": 30,
+                "timeouts": 1,
+                "sleeps": 2
+            })
+        );
+    }
+
+    #[test]
+    fn test_evicting_a_key_keeps_its_spend_window() {
+        let limiter = RateLimiter::new();
+        limiter.set_max_keys(1);
+        assert!(limiter.check_rate_limit("user").allowed);
+        assert!(limiter.check_cost_micros("user", 8, 0.00001, 60).allowed);
+
+        std::thread::sleep(Duration::from_millis(2));
+        assert!(limiter.check_rate_limit("other").allowed);
+        assert!(limiter.get_key_stats("user").is_none());
+
+        // The $0.000008 already spent still counts against the budget
+        let denied = limiter.check_cost_micros("user", 3, 0.00001, 60);
+        assert!(!denied.allowed);
+        assert_eq!(denied.kind, AdmissionKind::OverBudget);
+    }
+}
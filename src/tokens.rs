@@ -1,17 +1,22 @@
 /// Token counting functionality using tiktoken-rs
 use std::collections::HashMap;
-use std::sync::RwLock;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Arc, RwLock};
+use std::time::Instant;
 use tiktoken_rs::{cl100k_base, o200k_base, p50k_base, p50k_edit, r50k_base, CoreBPE};
 
 use crate::pricing;
 
 /// Cached encodings for different model families
+///
+/// Each encoding is loaded once and shared as an `Arc` by every model that
+/// maps to it, so handing one out never copies the BPE tables.
 struct EncodingCache {
-    cl100k: Option<CoreBPE>,    // GPT-4, GPT-3.5-turbo, text-embedding-ada-002
-    o200k: Option<CoreBPE>,     // GPT-4o, o1 models
-    p50k: Option<CoreBPE>,      // Codex models
-    p50k_edit: Option<CoreBPE>, // text-davinci-edit
-    r50k: Option<CoreBPE>,      // GPT-3 models
+    cl100k: Option<Arc<CoreBPE>>, // GPT-4, GPT-3.5-turbo, text-embedding-ada-002
+    o200k: Option<Arc<CoreBPE>>,  // GPT-4o, o1 models
+    p50k: Option<Arc<CoreBPE>>,   // Codex models
+    p50k_edit: Option<Arc<CoreBPE>>, // text-davinci-edit
+    r50k: Option<Arc<CoreBPE>>,   // GPT-3 models
 }
 
 impl EncodingCache {
@@ -26,74 +31,59 @@ impl EncodingCache {
     }
 
     /// Get cached encoding without initializing (for read-only access)
-    fn get_cached_encoding(&self, encoding_type: &str) -> Option<&CoreBPE> {
+    fn get_cached_encoding(&self, encoding_type: &str) -> Option<Arc<CoreBPE>> {
         match encoding_type {
-            "cl100k_base" => self.cl100k.as_ref(),
-            "o200k_base" => self.o200k.as_ref(),
-            "p50k_base" => self.p50k.as_ref(),
-            "p50k_edit" => self.p50k_edit.as_ref(),
-            "r50k_base" => self.r50k.as_ref(),
-            _ => self.cl100k.as_ref(),
+            "cl100k_base" => self.cl100k.clone(),
+            "o200k_base" => self.o200k.clone(),
+            "p50k_base" => self.p50k.clone(),
+            "p50k_edit" => self.p50k_edit.clone(),
+            "r50k_base" => self.r50k.clone(),
+            _ => self.cl100k.clone(),
         }
     }
 
-    fn get_encoding(&mut self, model: &str) -> Result<&CoreBPE, String> {
+    fn slot_mut(&mut self, encoding_type: &str) -> &mut Option<Arc<CoreBPE>> {
+        match encoding_type {
+            "o200k_base" => &mut self.o200k,
+            "p50k_base" => &mut self.p50k,
+            "p50k_edit" => &mut self.p50k_edit,
+            "r50k_base" => &mut self.r50k,
+            // Default to cl100k_base
+            _ => &mut self.cl100k,
+        }
+    }
+
+    fn get_encoding(&mut self, model: &str) -> Result<Arc<CoreBPE>, String> {
         // Map model names to encoding types
         let encoding_type = Self::model_to_encoding(model);
 
-        match encoding_type {
-            "cl100k_base" => {
-                if self.cl100k.is_none() {
-                    self.cl100k = Some(
-                        cl100k_base().map_err(|e| format!("Failed to load cl100k_base: {}", e))?,
-                    );
-                }
-                Ok(self.cl100k.as_ref().unwrap())
-            }
-            "o200k_base" => {
-                if self.o200k.is_none() {
-                    self.o200k = Some(
-                        o200k_base().map_err(|e| format!("Failed to load o200k_base: {}", e))?,
-                    );
-                }
-                Ok(self.o200k.as_ref().unwrap())
-            }
-            "p50k_base" => {
-                if self.p50k.is_none() {
-                    self.p50k =
-                        Some(p50k_base().map_err(|e| format!("Failed to load p50k_base: {}", e))?);
-                }
-                Ok(self.p50k.as_ref().unwrap())
-            }
-            "p50k_edit" => {
-                if self.p50k_edit.is_none() {
-                    self.p50k_edit =
-                        Some(p50k_edit().map_err(|e| format!("Failed to load p50k_edit: {}", e))?);
-                }
-                Ok(self.p50k_edit.as_ref().unwrap())
-            }
-            "r50k_base" => {
-                if self.r50k.is_none() {
-                    self.r50k =
-                        Some(r50k_base().map_err(|e| format!("Failed to load r50k_base: {}", e))?);
-                }
-                Ok(self.r50k.as_ref().unwrap())
-            }
-            _ => {
-                // Default to cl100k_base
-                if self.cl100k.is_none() {
-                    self.cl100k = Some(
-                        cl100k_base().map_err(|e| format!("Failed to load cl100k_base: {}", e))?,
-                    );
-                }
-                Ok(self.cl100k.as_ref().unwrap())
-            }
+        let slot = self.slot_mut(encoding_type);
+        if let Some(encoding) = slot {
+            return Ok(Arc::clone(encoding));
+        }
+        let encoding = Arc::new(load_encoding(encoding_type)?);
+        *slot = Some(Arc::clone(&encoding));
+        Ok(encoding)
+    }
+
+    /// Store an encoding loaded outside the lock, keeping any existing one
+    fn store_encoding(&mut self, encoding_type: &str, encoding: CoreBPE) {
+        let slot = self.slot_mut(encoding_type);
+        if slot.is_none() {
+            *slot = Some(Arc::new(encoding));
         }
     }
 
     fn model_to_encoding(model: &str) -> &'static str {
         let model_lower = model.to_lowercase();
 
+        // Runtime overrides take precedence over the built-in mapping
+        if let Ok(overrides) = ENCODING_OVERRIDES.read() {
+            if let Some(encoding) = overrides.get(&model_lower) {
+                return encoding;
+            }
+        }
+
         // o200k_base models (GPT-4o, o1 series) - use starts_with for safety
         if model_lower.starts_with("gpt-4o")
             || model_lower.starts_with("o1-")
@@ -136,8 +126,77 @@ impl EncodingCache {
     }
 }
 
+/// Tokens added per chat message for role/formatting (OpenAI chat format)
+const TOKENS_PER_MESSAGE: usize = 3;
+/// Extra token when a message carries a `name`
+const TOKENS_PER_NAME: usize = 1;
+/// Tokens priming the assistant reply at the end of every conversation
+const REPLY_PRIMING_TOKENS: usize = 3;
+
+/// A chat message reduced to the parts that count towards the prompt
+#[derive(Debug, Clone, Default)]
+pub struct ChatMessage {
+    pub role: String,
+    pub content: String,
+    pub name: Option<String>,
+}
+
+/// Default share of the context window assumed for the response when a
+/// request carries no `max_tokens`/`max_completion_tokens`
+const DEFAULT_OUTPUT_FRACTION: f64 = 0.1;
+
+/// Inputs larger than this are counted with `count_tokens_approx` instead of
+/// the BPE encoder, which can be slow on very long or adversarial input
+const DEFAULT_MAX_ENCODE_BYTES: usize = 8 * 1024 * 1024;
+
+/// Average ASCII bytes per token and tokens per non-ASCII character for an
+/// encoding, calibrated on English prose
+fn approx_ratios(encoding_type: &str) -> (f64, f64) {
+    match encoding_type {
+        "o200k_base" => (4.9, 0.75),
+        "p50k_base" | "p50k_edit" | "r50k_base" => (4.3, 1.5),
+        _ => (4.8, 1.0),
+    }
+}
+
+/// Every id below this is a token of the encoding; specials past it are
+/// checked separately (p50k keeps `<|endoftext|>` at 50256 inside the range)
+fn ordinary_vocab_size(encoding_type: &str) -> u32 {
+    match encoding_type {
+        "o200k_base" => 199_998,
+        "p50k_base" | "p50k_edit" => 50_281,
+        "r50k_base" => 50_256,
+        _ => 100_256,
+    }
+}
+
+/// Whether `token_id` is an ordinary or special token of the encoding
+fn is_known_token(encoding_type: &str, encoding: &CoreBPE, token_id: u32) -> bool {
+    token_id < ordinary_vocab_size(encoding_type)
+        || encoding
+            .special_tokens()
+            .into_iter()
+            .any(|name| encoding.encode_with_special_tokens(name) == [token_id])
+}
+
+/// Build a tiktoken encoding by name (expensive, callers should cache it)
+fn load_encoding(encoding_type: &str) -> Result<CoreBPE, String> {
+    let encoding = match encoding_type {
+        "o200k_base" => o200k_base(),
+        "p50k_base" => p50k_base(),
+        "p50k_edit" => p50k_edit(),
+        "r50k_base" => r50k_base(),
+        _ => cl100k_base(),
+    };
+    encoding.map_err(|e| format!("Failed to load {}: {}", encoding_type, e))
+}
+
 pub struct TokenCounter {
     cache: RwLock<EncodingCache>,
+    /// f64 bits of the output fraction used by `estimate_output_tokens`
+    default_output_fraction: AtomicU64,
+    /// Largest input (in bytes) handed to the BPE encoder when counting
+    max_encode_bytes: AtomicUsize,
 }
 
 impl Default for TokenCounter {
@@ -150,11 +209,94 @@ impl TokenCounter {
     pub fn new() -> Self {
         Self {
             cache: RwLock::new(EncodingCache::new()),
+            default_output_fraction: AtomicU64::new(DEFAULT_OUTPUT_FRACTION.to_bits()),
+            max_encode_bytes: AtomicUsize::new(DEFAULT_MAX_ENCODE_BYTES),
         }
     }
 
-    pub fn count_tokens(&self, text: &str, model: Option<&str>) -> Result<usize, String> {
-        let model = model.unwrap_or("gpt-3.5-turbo");
+    /// Set the share of the context window assumed for the response when no
+    /// explicit limit is given (clamped to 0.0..=1.0)
+    pub fn set_default_output_fraction(&self, fraction: f64) {
+        let fraction = if fraction.is_finite() {
+            fraction.clamp(0.0, 1.0)
+        } else {
+            DEFAULT_OUTPUT_FRACTION
+        };
+        self.default_output_fraction
+            .store(fraction.to_bits(), Ordering::Relaxed);
+    }
+
+    /// Set the largest input counted with the BPE encoder; `None` removes the limit
+    ///
+    /// Larger inputs fall back to `count_tokens_approx`, so a hostile request
+    /// can't tie up the encoder.
+    pub fn set_max_encode_bytes(&self, max_bytes: Option<usize>) {
+        self.max_encode_bytes
+            .store(max_bytes.unwrap_or(usize::MAX), Ordering::Relaxed);
+    }
+
+    /// Whether `text` is too large to encode, warning (once) if so
+    fn exceeds_encode_limit(&self, text: &str) -> bool {
+        self.exceeds_encode_limit_bytes(text.len())
+    }
+
+    /// Whether `bytes` of input are too large to encode, warning (once) if so
+    fn exceeds_encode_limit_bytes(&self, bytes: usize) -> bool {
+        let limit = self.max_encode_bytes.load(Ordering::Relaxed);
+        if bytes <= limit {
+            return false;
+        }
+
+        static WARNED: std::sync::Once = std::sync::Once::new();
+        WARNED.call_once(|| {
+            eprintln!(
+                "WARNING: Input of {} bytes exceeds max_encode_bytes ({}) - \
+                using approximate token count instead of the encoder",
+                bytes, limit
+            );
+        });
+        true
+    }
+
+    /// Estimate response tokens for routing budgets and context-fit checks
+    ///
+    /// Precedence:
+    /// 1. `max_completion_tokens` from the request
+    /// 2. `max_tokens` from the request
+    /// 3. the default output fraction of the model's context window, capped
+    ///    at the model's max output tokens
+    pub fn estimate_output_tokens(
+        &self,
+        model: &str,
+        max_tokens: Option<usize>,
+        max_completion_tokens: Option<usize>,
+    ) -> usize {
+        if let Some(tokens) = max_completion_tokens.or(max_tokens) {
+            return tokens;
+        }
+
+        let limits = self.get_model_limits(model);
+        let context_window = limits
+            .get("context_window")
+            .and_then(|v| v.as_u64())
+            .unwrap_or(0);
+        let max_output = limits
+            .get("max_output_tokens")
+            .and_then(|v| v.as_u64())
+            .unwrap_or(u64::MAX);
+
+        let fraction = f64::from_bits(self.default_output_fraction.load(Ordering::Relaxed));
+        let estimate = (context_window as f64 * fraction) as u64;
+        estimate.min(max_output) as usize
+    }
+
+    /// Name of the encoding `model` resolves to (after runtime overrides)
+    pub fn encoding_for_model(&self, model: &str) -> &'static str {
+        EncodingCache::model_to_encoding(model)
+    }
+
+    /// Shared handle to the encoding for `model`, loading it on first use
+    fn encoding(&self, model: &str) -> Result<Arc<CoreBPE>, String> {
         let encoding_type = EncodingCache::model_to_encoding(model);
 
         // Try read lock first (fast path)
@@ -164,8 +306,7 @@ impl TokenCounter {
                 .read()
                 .map_err(|e| format!("Lock error: {}", e))?;
             if let Some(encoding) = cache.get_cached_encoding(encoding_type) {
-                let tokens = encoding.encode_with_special_tokens(text);
-                return Ok(tokens.len());
+                return Ok(encoding);
             }
         }
 
@@ -174,9 +315,110 @@ impl TokenCounter {
             .cache
             .write()
             .map_err(|e| format!("Lock error: {}", e))?;
-        let encoding = cache.get_encoding(model)?;
-        let tokens = encoding.encode_with_special_tokens(text);
-        Ok(tokens.len())
+        cache.get_encoding(model)
+    }
+
+    /// Run `f` with the encoding for `model`; the cache lock is not held
+    /// while `f` runs
+    fn with_encoding<R>(&self, model: &str, f: impl FnOnce(&CoreBPE) -> R) -> Result<R, String> {
+        let encoding = self.encoding(model)?;
+        Ok(f(&encoding))
+    }
+
+    pub fn count_tokens(&self, text: &str, model: Option<&str>) -> Result<usize, String> {
+        let model = model.unwrap_or("gpt-3.5-turbo");
+        if self.exceeds_encode_limit(text) {
+            return Ok(self.count_tokens_approx(text, Some(model)));
+        }
+        self.with_encoding(model, |encoding| {
+            encoding.encode_with_special_tokens(text).len()
+        })
+    }
+
+    /// Raw bytes of a single token
+    ///
+    /// Unlike a full decode this doesn't require valid UTF-8, so partial
+    /// (byte-level) tokens come back intact.
+    pub fn decode_single(&self, token_id: u32, model: &str) -> Result<Vec<u8>, String> {
+        let encoding_type = EncodingCache::model_to_encoding(model);
+        self.with_encoding(model, |encoding| {
+            // The raw lookup panics on ids outside the vocabulary, so it only
+            // runs once the id is known to exist
+            if !is_known_token(encoding_type, encoding, token_id) {
+                return Err(format!("Unknown token id {}", token_id));
+            }
+            encoding
+                ._decode_native_and_split(vec![token_id])
+                .next()
+                .ok_or_else(|| format!("Unknown token id {}", token_id))
+        })?
+    }
+
+    /// Token count of a fixed prompt prefix, to be reused with `count_with_prefix`
+    pub fn count_prefix(&self, prefix: &str, model: &str) -> Result<usize, String> {
+        self.count_tokens(prefix, Some(model))
+    }
+
+    /// Token count of `prefix + suffix` given the prefix's precomputed count
+    ///
+    /// Only the suffix is encoded. Exact when the prefix ends on a token
+    /// boundary (e.g. a newline or separator); otherwise BPE may merge across
+    /// the join and the true count can be slightly lower.
+    pub fn count_with_prefix(
+        &self,
+        prefix_token_count: usize,
+        suffix: &str,
+        model: &str,
+    ) -> Result<usize, String> {
+        Ok(prefix_token_count + self.count_tokens(suffix, Some(model))?)
+    }
+
+    /// Length of the shared token prefix of `a` and `b`
+    ///
+    /// Prompt caches match on tokens, not characters, so this is the number
+    /// of tokens of `b` that could be served from a cache populated by `a`.
+    /// Inputs over `max_encode_bytes` get an approximate count of their shared
+    /// character prefix instead.
+    pub fn common_prefix_tokens(&self, a: &str, b: &str, model: &str) -> Result<usize, String> {
+        if self.exceeds_encode_limit(a) || self.exceeds_encode_limit(b) {
+            let shared_bytes: usize = a
+                .chars()
+                .zip(b.chars())
+                .take_while(|(x, y)| x == y)
+                .map(|(c, _)| c.len_utf8())
+                .sum();
+            return Ok(self.count_tokens_approx(&a[..shared_bytes], Some(model)));
+        }
+        self.with_encoding(model, |encoding| {
+            let a_tokens = encoding.encode_with_special_tokens(a);
+            let b_tokens = encoding.encode_with_special_tokens(b);
+            a_tokens
+                .iter()
+                .zip(&b_tokens)
+                .take_while(|(x, y)| x == y)
+                .count()
+        })
+    }
+
+    /// Approximate token count without running the BPE encoder
+    ///
+    /// Uses per-encoding byte/character ratios, so it never touches the
+    /// encoding cache. Typically within ±10% of `count_tokens` for prose;
+    /// use it where throughput matters more than exactness (logging, metrics).
+    pub fn count_tokens_approx(&self, text: &str, model: Option<&str>) -> usize {
+        if text.is_empty() {
+            return 0;
+        }
+        let model = model.unwrap_or("gpt-3.5-turbo");
+        let (bytes_per_token, tokens_per_char) =
+            approx_ratios(EncodingCache::model_to_encoding(model));
+
+        let ascii_bytes = text.bytes().filter(u8::is_ascii).count();
+        let non_ascii_chars = text.chars().filter(|c| !c.is_ascii()).count();
+        let estimate =
+            ascii_bytes as f64 / bytes_per_token + non_ascii_chars as f64 * tokens_per_char;
+
+        (estimate.round() as usize).max(1)
     }
 
     pub fn count_tokens_batch(
@@ -185,36 +427,154 @@ impl TokenCounter {
         model: Option<&str>,
     ) -> Result<Vec<usize>, String> {
         let model = model.unwrap_or("gpt-3.5-turbo");
-        let encoding_type = EncodingCache::model_to_encoding(model);
+        self.with_encoding(model, |encoding| {
+            texts
+                .iter()
+                .map(|text| {
+                    if self.exceeds_encode_limit(text) {
+                        self.count_tokens_approx(text, Some(model))
+                    } else {
+                        encoding.encode_with_special_tokens(text).len()
+                    }
+                })
+                .collect()
+        })
+    }
 
-        // Try read lock first (fast path)
-        {
-            let cache = self
-                .cache
-                .read()
-                .map_err(|e| format!("Lock error: {}", e))?;
-            if let Some(encoding) = cache.get_cached_encoding(encoding_type) {
-                let results: Vec<usize> = texts
+    /// Load the encodings for `models` concurrently and cache them
+    ///
+    /// Each distinct encoding is built once outside the cache lock, on at
+    /// most `available_parallelism` threads at a time. Returns per-model
+    /// success, encoding name and load time (models sharing an encoding
+    /// report the same load).
+    pub fn preload_models_parallel(&self, models: &[String]) -> HashMap<String, serde_json::Value> {
+        let mut encoding_types: Vec<&'static str> = models
+            .iter()
+            .map(|model| EncodingCache::model_to_encoding(model))
+            .collect();
+        encoding_types.sort_unstable();
+        encoding_types.dedup();
+
+        let workers = std::thread::available_parallelism()
+            .map(|n| n.get())
+            .unwrap_or(1);
+        let mut loaded: Vec<(&'static str, Result<CoreBPE, String>, f64)> = Vec::new();
+        for batch in encoding_types.chunks(workers) {
+            std::thread::scope(|scope| {
+                let handles: Vec<_> = batch
                     .iter()
-                    .map(|text| encoding.encode_with_special_tokens(text).len())
+                    .map(|&encoding_type| {
+                        scope.spawn(move || {
+                            let start = Instant::now();
+                            let result = load_encoding(encoding_type);
+                            (
+                                encoding_type,
+                                result,
+                                start.elapsed().as_secs_f64() * 1000.0,
+                            )
+                        })
+                    })
                     .collect();
-                return Ok(results);
-            }
+
+                loaded.extend(handles.into_iter().map(|handle| {
+                    handle.join().unwrap_or_else(|_| {
+                        ("unknown", Err("Encoding load panicked".to_string()), 0.0)
+                    })
+                }));
+            });
         }
 
-        // Need to initialize - use write lock
-        let mut cache = self
-            .cache
-            .write()
-            .map_err(|e| format!("Lock error: {}", e))?;
-        let encoding = cache.get_encoding(model)?;
+        let mut outcomes: HashMap<&'static str, serde_json::Value> = HashMap::new();
+        {
+            let mut cache = match self.cache.write() {
+                Ok(cache) => cache,
+                Err(poisoned) => poisoned.into_inner(),
+            };
+            for (encoding_type, result, load_time_ms) in loaded {
+                let outcome = match result {
+                    Ok(encoding) => {
+                        cache.store_encoding(encoding_type, encoding);
+                        serde_json::json!({
+                            "success": true,
+                            "encoding": encoding_type,
+                            "load_time_ms": load_time_ms
+                        })
+                    }
+                    Err(e) => serde_json::json!({
+                        "success": false,
+                        "encoding": encoding_type,
+                        "load_time_ms": load_time_ms,
+                        "error": e
+                    }),
+                };
+                outcomes.insert(encoding_type, outcome);
+            }
+        }
 
-        let results: Vec<usize> = texts
+        models
             .iter()
-            .map(|text| encoding.encode_with_special_tokens(text).len())
-            .collect();
+            .map(|model| {
+                let encoding_type = EncodingCache::model_to_encoding(model);
+                let outcome = outcomes.get(encoding_type).cloned().unwrap_or_else(|| {
+                    serde_json::json!({
+                        "success": false,
+                        "encoding": encoding_type,
+                        "error": "Encoding load did not complete"
+                    })
+                });
+                (model.clone(), outcome)
+            })
+            .collect()
+    }
+
+    /// Time `iterations` encodes of `sample_text` and report throughput
+    ///
+    /// The encoding is loaded before timing starts, so the numbers reflect
+    /// steady-state encode speed only. Samples over `max_encode_bytes` are
+    /// rejected rather than encoded.
+    pub fn benchmark_encode(
+        &self,
+        sample_text: &str,
+        model: &str,
+        iterations: usize,
+    ) -> Result<HashMap<String, serde_json::Value>, String> {
+        if self.exceeds_encode_limit(sample_text) {
+            return Err(format!(
+                "Sample of {} bytes exceeds max_encode_bytes ({})",
+                sample_text.len(),
+                self.max_encode_bytes.load(Ordering::Relaxed)
+            ));
+        }
+        let iterations = iterations.max(1);
 
-        Ok(results)
+        let (total_tokens, elapsed) = self.with_encoding(model, |encoding| {
+            let start = Instant::now();
+            let mut total_tokens = 0usize;
+            for _ in 0..iterations {
+                total_tokens += encoding.encode_with_special_tokens(sample_text).len();
+            }
+            (total_tokens, start.elapsed())
+        })?;
+
+        let wall_time_secs = elapsed.as_secs_f64();
+        let tokens_per_sec = if wall_time_secs > 0.0 {
+            total_tokens as f64 / wall_time_secs
+        } else {
+            0.0
+        };
+
+        let mut result = HashMap::new();
+        result.insert(
+            "tokens_per_sec".to_string(),
+            serde_json::json!(tokens_per_sec),
+        );
+        result.insert("total_tokens".to_string(), serde_json::json!(total_tokens));
+        result.insert("iterations".to_string(), serde_json::json!(iterations));
+        result.insert(
+            "wall_time_ms".to_string(),
+            serde_json::json!(wall_time_secs * 1000.0),
+        );
+        Ok(result)
     }
 
     pub fn estimate_cost(
@@ -259,6 +619,30 @@ impl TokenCounter {
         Ok(input_cost + output_cost)
     }
 
+    /// Cost of a completed request from its reported usage
+    ///
+    /// `cached_tokens` are the part of `prompt_tokens` served from the prompt
+    /// cache and are billed at the model's cache-read rate when it has one,
+    /// otherwise at the regular input rate.
+    pub fn cost_from_usage(
+        &self,
+        model: &str,
+        prompt_tokens: usize,
+        completion_tokens: usize,
+        cached_tokens: usize,
+    ) -> Result<f64, String> {
+        let cached_tokens = cached_tokens.min(prompt_tokens);
+        let regular_cost =
+            self.estimate_cost(prompt_tokens - cached_tokens, completion_tokens, model)?;
+
+        let cached_cost = match pricing::get_pricing_data().get_cached_input_cost_per_1m(model) {
+            Some(cost_per_1m) => cached_tokens as f64 * cost_per_1m / 1_000_000.0,
+            None => self.estimate_cost(cached_tokens, 0, model)?,
+        };
+
+        Ok(regular_cost + cached_cost)
+    }
+
     pub fn get_model_limits(&self, model: &str) -> HashMap<String, serde_json::Value> {
         let mut limits = HashMap::new();
 
@@ -308,17 +692,239 @@ impl TokenCounter {
 
         Ok(true)
     }
+
+    /// Count completion (response) tokens for generated text
+    ///
+    /// Completion text is raw model output, so unlike `count_message_tokens`
+    /// no per-message or reply-priming overhead is added.
+    pub fn count_completion_tokens(&self, text: &str, model: &str) -> Result<usize, String> {
+        self.count_tokens(text, Some(model))
+    }
+
+    /// Check `count_tokens` against known-good `(model, text, expected)` counts
+    ///
+    /// Meant for CI fixtures that catch a model silently changing encoding.
+    /// Returns pass/fail totals and, per case in input order, the expected
+    /// and actual counts plus the encoding used.
+    pub fn verify_counts(
+        &self,
+        cases: &[(String, String, usize)],
+    ) -> HashMap<String, serde_json::Value> {
+        let mut passed = 0;
+        let results: Vec<serde_json::Value> = cases
+            .iter()
+            .map(|(model, text, expected)| {
+                let mut result = serde_json::json!({
+                    "model": model,
+                    "encoding": self.encoding_for_model(model),
+                    "expected": expected,
+                });
+                match self.count_tokens(text, Some(model)) {
+                    Ok(actual) => {
+                        result["actual"] = serde_json::json!(actual);
+                        result["passed"] = serde_json::json!(actual == *expected);
+                        if actual == *expected {
+                            passed += 1;
+                        }
+                    }
+                    Err(e) => {
+                        result["actual"] = serde_json::Value::Null;
+                        result["passed"] = serde_json::json!(false);
+                        result["error"] = serde_json::json!(e);
+                    }
+                }
+                result
+            })
+            .collect();
+
+        let mut summary = HashMap::new();
+        summary.insert("passed".to_string(), serde_json::json!(passed));
+        summary.insert(
+            "failed".to_string(),
+            serde_json::json!(cases.len() - passed),
+        );
+        summary.insert("results".to_string(), serde_json::json!(results));
+        summary
+    }
+
+    /// Count `text` and price it as input or output tokens in one pass
+    ///
+    /// Returns `(tokens, cost_usd)`; pricing falls back to the defaults for
+    /// unknown models, as in `estimate_cost`.
+    pub fn count_and_cost(
+        &self,
+        text: &str,
+        model: &str,
+        is_output: bool,
+    ) -> Result<(usize, f64), String> {
+        let tokens = self.count_tokens(text, Some(model))?;
+        let cost = if is_output {
+            self.estimate_cost(0, tokens, model)?
+        } else {
+            self.estimate_cost(tokens, 0, model)?
+        };
+        Ok((tokens, cost))
+    }
+
+    /// Count prompt tokens for a list of chat messages, including the
+    /// per-message and reply-priming overhead of the chat format
+    ///
+    /// Conversations whose text adds up to more than `max_encode_bytes` are
+    /// counted with `count_tokens_approx`.
+    pub fn count_message_tokens(
+        &self,
+        messages: &[ChatMessage],
+        model: Option<&str>,
+    ) -> Result<usize, String> {
+        let mut texts = Vec::with_capacity(messages.len() * 2);
+        let mut overhead = REPLY_PRIMING_TOKENS;
+
+        for message in messages {
+            overhead += TOKENS_PER_MESSAGE;
+            texts.push(message.role.clone());
+            texts.push(message.content.clone());
+            if let Some(name) = &message.name {
+                overhead += TOKENS_PER_NAME;
+                texts.push(name.clone());
+            }
+        }
+
+        let total_bytes = texts.iter().map(String::len).sum();
+        if self.exceeds_encode_limit_bytes(total_bytes) {
+            let approx: usize = texts
+                .iter()
+                .map(|text| self.count_tokens_approx(text, model))
+                .sum();
+            return Ok(approx + overhead);
+        }
+
+        let counts = self.count_tokens_batch(&texts, model)?;
+        Ok(counts.iter().sum::<usize>() + overhead)
+    }
+
+    /// `count_message_tokens` plus a precomputed image token count
+    ///
+    /// For callers that already know the image cost (e.g. from the provider's
+    /// usage report); image parts in `messages` are otherwise not counted.
+    pub fn count_message_tokens_with_images(
+        &self,
+        messages: &[ChatMessage],
+        model: Option<&str>,
+        image_tokens: usize,
+    ) -> Result<usize, String> {
+        Ok(self.count_message_tokens(messages, model)? + image_tokens)
+    }
+
+    /// Tokens that can still be added to a conversation before hitting the
+    /// model's context window, keeping `reserve_output` tokens for the reply.
+    /// Negative when the conversation is already over budget.
+    pub fn remaining_budget(
+        &self,
+        messages: &[ChatMessage],
+        model: &str,
+        reserve_output: usize,
+    ) -> Result<i64, String> {
+        let used = self.count_message_tokens(messages, Some(model))?;
+        let context_window = self
+            .get_model_limits(model)
+            .get("context_window")
+            .and_then(|v| v.as_u64())
+            .unwrap_or(0);
+
+        Ok(context_window as i64 - used as i64 - reserve_output as i64)
+    }
+}
+
+/// Truncate `text` to at most `max_bytes` bytes without splitting a UTF-8 character
+pub fn truncate_to_bytes(text: &str, max_bytes: usize) -> String {
+    if text.len() <= max_bytes {
+        return text.to_string();
+    }
+
+    let mut end = max_bytes;
+    while !text.is_char_boundary(end) {
+        end -= 1;
+    }
+    text[..end].to_string()
 }
 
 // Global token counter instance
 lazy_static::lazy_static! {
     static ref TOKEN_COUNTER: TokenCounter = TokenCounter::new();
+    /// Lowercased model name -> encoding, consulted before the built-in mapping
+    static ref ENCODING_OVERRIDES: RwLock<HashMap<String, &'static str>> =
+        RwLock::new(HashMap::new());
+}
+
+/// Encodings that can be loaded by name
+const ENCODING_NAMES: [&str; 5] = [
+    "cl100k_base",
+    "o200k_base",
+    "p50k_base",
+    "p50k_edit",
+    "r50k_base",
+];
+
+/// Map a model to an encoding ahead of the built-in lookup (e.g. for models
+/// newer than the bundled mapping)
+pub fn register_model_encoding(model: &str, encoding: &str) -> Result<(), String> {
+    let encoding = ENCODING_NAMES
+        .iter()
+        .find(|name| **name == encoding)
+        .ok_or_else(|| {
+            format!(
+                "Unknown encoding '{}' (expected one of: {})",
+                encoding,
+                ENCODING_NAMES.join(", ")
+            )
+        })?;
+
+    ENCODING_OVERRIDES
+        .write()
+        .map_err(|e| format!("Lock error: {}", e))?
+        .insert(model.to_lowercase(), encoding);
+    Ok(())
+}
+
+/// Remove all runtime model-to-encoding overrides
+pub fn clear_model_encoding_overrides() {
+    if let Ok(mut overrides) = ENCODING_OVERRIDES.write() {
+        overrides.clear();
+    }
 }
 
 pub fn count_tokens(text: &str, model: Option<&str>) -> Result<usize, String> {
     TOKEN_COUNTER.count_tokens(text, model)
 }
 
+pub fn encoding_for_model(model: &str) -> &'static str {
+    TOKEN_COUNTER.encoding_for_model(model)
+}
+
+pub fn decode_single(token_id: u32, model: &str) -> Result<Vec<u8>, String> {
+    TOKEN_COUNTER.decode_single(token_id, model)
+}
+
+pub fn count_prefix(prefix: &str, model: &str) -> Result<usize, String> {
+    TOKEN_COUNTER.count_prefix(prefix, model)
+}
+
+pub fn count_with_prefix(
+    prefix_token_count: usize,
+    suffix: &str,
+    model: &str,
+) -> Result<usize, String> {
+    TOKEN_COUNTER.count_with_prefix(prefix_token_count, suffix, model)
+}
+
+pub fn common_prefix_tokens(a: &str, b: &str, model: &str) -> Result<usize, String> {
+    TOKEN_COUNTER.common_prefix_tokens(a, b, model)
+}
+
+pub fn count_tokens_approx(text: &str, model: Option<&str>) -> usize {
+    TOKEN_COUNTER.count_tokens_approx(text, model)
+}
+
 pub fn count_tokens_batch(texts: &[String], model: Option<&str>) -> Result<Vec<usize>, String> {
     TOKEN_COUNTER.count_tokens_batch(texts, model)
 }
@@ -331,6 +937,15 @@ pub fn estimate_cost(
     TOKEN_COUNTER.estimate_cost(input_tokens, output_tokens, model)
 }
 
+pub fn cost_from_usage(
+    model: &str,
+    prompt_tokens: usize,
+    completion_tokens: usize,
+    cached_tokens: usize,
+) -> Result<f64, String> {
+    TOKEN_COUNTER.cost_from_usage(model, prompt_tokens, completion_tokens, cached_tokens)
+}
+
 pub fn get_model_limits(model: &str) -> HashMap<String, serde_json::Value> {
     TOKEN_COUNTER.get_model_limits(model)
 }
@@ -339,6 +954,69 @@ pub fn validate_input(text: &str, model: &str) -> Result<bool, String> {
     TOKEN_COUNTER.validate_input(text, model)
 }
 
+pub fn set_default_output_fraction(fraction: f64) {
+    TOKEN_COUNTER.set_default_output_fraction(fraction);
+}
+
+pub fn set_max_encode_bytes(max_bytes: Option<usize>) {
+    TOKEN_COUNTER.set_max_encode_bytes(max_bytes);
+}
+
+pub fn estimate_output_tokens(
+    model: &str,
+    max_tokens: Option<usize>,
+    max_completion_tokens: Option<usize>,
+) -> usize {
+    TOKEN_COUNTER.estimate_output_tokens(model, max_tokens, max_completion_tokens)
+}
+
+pub fn preload_models_parallel(models: &[String]) -> HashMap<String, serde_json::Value> {
+    TOKEN_COUNTER.preload_models_parallel(models)
+}
+
+pub fn benchmark_encode(
+    sample_text: &str,
+    model: &str,
+    iterations: usize,
+) -> Result<HashMap<String, serde_json::Value>, String> {
+    TOKEN_COUNTER.benchmark_encode(sample_text, model, iterations)
+}
+
+pub fn count_completion_tokens(text: &str, model: &str) -> Result<usize, String> {
+    TOKEN_COUNTER.count_completion_tokens(text, model)
+}
+
+pub fn verify_counts(cases: &[(String, String, usize)]) -> HashMap<String, serde_json::Value> {
+    TOKEN_COUNTER.verify_counts(cases)
+}
+
+pub fn count_and_cost(text: &str, model: &str, is_output: bool) -> Result<(usize, f64), String> {
+    TOKEN_COUNTER.count_and_cost(text, model, is_output)
+}
+
+pub fn count_message_tokens(
+    messages: &[ChatMessage],
+    model: Option<&str>,
+) -> Result<usize, String> {
+    TOKEN_COUNTER.count_message_tokens(messages, model)
+}
+
+pub fn count_message_tokens_with_images(
+    messages: &[ChatMessage],
+    model: Option<&str>,
+    image_tokens: usize,
+) -> Result<usize, String> {
+    TOKEN_COUNTER.count_message_tokens_with_images(messages, model, image_tokens)
+}
+
+pub fn remaining_budget(
+    messages: &[ChatMessage],
+    model: &str,
+    reserve_output: usize,
+) -> Result<i64, String> {
+    TOKEN_COUNTER.remaining_budget(messages, model, reserve_output)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -367,6 +1045,171 @@ mod tests {
         assert_eq!(counts.len(), 3);
     }
 
+    #[test]
+    fn test_count_tokens_approx() {
+        let counter = TokenCounter::new();
+        let text = "The quick brown fox jumps over the lazy dog. Rate limits are applied \
+                    per key, and the router picks a healthy endpoint for every request \
+                    before the connection pool hands out a connection to it.";
+
+        for model in ["gpt-3.5-turbo", "gpt-4o"] {
+            let exact = counter.count_tokens(text, Some(model)).unwrap() as f64;
+            let approx = counter.count_tokens_approx(text, Some(model)) as f64;
+            assert!(
+                (approx - exact).abs() / exact <= 0.1,
+                "{}: approx {} vs exact {}",
+                model,
+                approx,
+                exact
+            );
+        }
+        assert_eq!(counter.count_tokens_approx("", None), 0);
+    }
+
+    #[test]
+    fn test_count_tokens_boundaries() {
+        let counter = TokenCounter::new();
+
+        for model in ["gpt-3.5-turbo", "gpt-4o"] {
+            assert_eq!(counter.count_tokens("", Some(model)).unwrap(), 0);
+            assert_eq!(counter.count_tokens_approx("", Some(model)), 0);
+
+            for text in [" ", "\n", "a"] {
+                assert_eq!(counter.count_tokens(text, Some(model)).unwrap(), 1);
+                assert_eq!(counter.count_tokens_approx(text, Some(model)), 1);
+            }
+        }
+    }
+
+    #[test]
+    fn test_decode_single() {
+        let counter = TokenCounter::new();
+        let model = "gpt-3.5-turbo";
+
+        let ids = counter
+            .with_encoding(model, |encoding| {
+                encoding.encode_with_special_tokens("hello")
+            })
+            .unwrap();
+        assert_eq!(counter.decode_single(ids[0], model).unwrap(), b"hello");
+
+        // A multi-byte character split across tokens decodes to partial bytes
+        let ids = counter
+            .with_encoding(model, |encoding| encoding.encode_with_special_tokens("🦀"))
+            .unwrap();
+        let bytes: Vec<u8> = ids
+            .iter()
+            .flat_map(|id| counter.decode_single(*id, model).unwrap())
+            .collect();
+        assert_eq!(bytes, "🦀".as_bytes());
+
+        assert!(counter.decode_single(u32::MAX, model).is_err());
+    }
+
+    #[test]
+    fn test_remaining_budget() {
+        let counter = TokenCounter::new();
+        let messages = vec![ChatMessage {
+            role: "user".to_string(),
+            content: "Hello, world!".to_string(),
+            name: None,
+        }];
+
+        let used = counter
+            .count_message_tokens(&messages, Some("gpt-4"))
+            .unwrap();
+        let plain = counter
+            .count_tokens("Hello, world!", Some("gpt-4"))
+            .unwrap();
+        assert!(used > plain);
+
+        let context_window = counter
+            .get_model_limits("gpt-4")
+            .get("context_window")
+            .and_then(|v| v.as_u64())
+            .unwrap() as i64;
+        let remaining = counter.remaining_budget(&messages, "gpt-4", 100).unwrap();
+        assert_eq!(remaining, context_window - used as i64 - 100);
+
+        let over = counter
+            .remaining_budget(&messages, "gpt-4", context_window as usize)
+            .unwrap();
+        assert!(over < 0);
+    }
+
+    #[test]
+    fn test_truncate_to_bytes() {
+        assert_eq!(truncate_to_bytes("hello", 10), "hello");
+        assert_eq!(truncate_to_bytes("hello", 3), "hel");
+        assert_eq!(truncate_to_bytes("hello", 0), "");
+
+        // "ab" + 4-byte emoji: budgets inside the emoji drop it entirely
+        let text = "ab\u{1F600}";
+        assert_eq!(text.len(), 6);
+        assert_eq!(truncate_to_bytes(text, 2), "ab");
+        assert_eq!(truncate_to_bytes(text, 3), "ab");
+        assert_eq!(truncate_to_bytes(text, 5), "ab");
+        assert_eq!(truncate_to_bytes(text, 6), text);
+
+        let emojis = "\u{1F600}\u{1F601}";
+        assert_eq!(truncate_to_bytes(emojis, 4), "\u{1F600}");
+        assert_eq!(truncate_to_bytes(emojis, 7), "\u{1F600}");
+    }
+
+    #[test]
+    fn test_register_model_encoding() {
+        assert!(register_model_encoding("future-model-x", "not_an_encoding").is_err());
+
+        assert_eq!(
+            EncodingCache::model_to_encoding("future-model-x"),
+            "cl100k_base"
+        );
+        register_model_encoding("Future-Model-X", "o200k_base").unwrap();
+        assert_eq!(
+            EncodingCache::model_to_encoding("future-model-x"),
+            "o200k_base"
+        );
+
+        clear_model_encoding_overrides();
+        assert_eq!(
+            EncodingCache::model_to_encoding("future-model-x"),
+            "cl100k_base"
+        );
+    }
+
+    #[test]
+    fn test_max_encode_bytes_falls_back_to_approx() {
+        let counter = TokenCounter::new();
+        let text = "a".repeat(64);
+        let exact = counter.count_tokens(&text, Some("gpt-4")).unwrap();
+
+        counter.set_max_encode_bytes(Some(32));
+        let approx = counter.count_tokens_approx(&text, Some("gpt-4"));
+        assert_eq!(counter.count_tokens(&text, Some("gpt-4")).unwrap(), approx);
+        assert_ne!(approx, exact);
+
+        counter.set_max_encode_bytes(None);
+        assert_eq!(counter.count_tokens(&text, Some("gpt-4")).unwrap(), exact);
+    }
+
+    #[test]
+    fn test_verify_counts() {
+        let counter = TokenCounter::new();
+        let cases = vec![
+            ("gpt-4".to_string(), "hello world".to_string(), 2),
+            ("gpt-4o".to_string(), "hello world".to_string(), 5),
+        ];
+        let summary = counter.verify_counts(&cases);
+        assert_eq!(summary["passed"], 1);
+        assert_eq!(summary["failed"], 1);
+
+        let results = summary["results"].as_array().unwrap();
+        assert_eq!(results[0]["passed"], true);
+        assert_eq!(results[0]["encoding"], "cl100k_base");
+        assert_eq!(results[1]["passed"], false);
+        assert_eq!(results[1]["actual"], 2);
+    }
+
     #[test]
     fn test_model_encoding_selection() {
         // Test that different models use appropriate encodings
@@ -381,4 +1224,130 @@ mod tests {
             "p50k_base"
         );
     }
+
+    #[test]
+    fn test_preload_models_parallel() {
+        let counter = TokenCounter::new();
+        let models: Vec<String> = ["gpt-4", "gpt-3.5-turbo", "gpt-4o", "text-davinci-003"]
+            .iter()
+            .map(|model| model.to_string())
+            .collect();
+
+        let result = counter.preload_models_parallel(&models);
+        assert_eq!(result.len(), models.len());
+        assert!(result.values().all(|outcome| outcome["success"] == true));
+        assert_eq!(result["gpt-4"]["encoding"], "cl100k_base");
+        assert_eq!(result["gpt-4o"]["encoding"], "o200k_base");
+        // Models sharing an encoding share its single load
+        assert_eq!(
+            result["gpt-4"]["load_time_ms"],
+            result["gpt-3.5-turbo"]["load_time_ms"]
+        );
+        assert_eq!(
+            counter.count_tokens("hello world", Some("gpt-4o")).unwrap(),
+            2
+        );
+    }
+
+    #[test]
+    fn test_decode_single_vocab_boundary() {
+        let counter = TokenCounter::new();
+        for (model, encoding_type) in [
+            ("gpt-4o", "o200k_base"),
+            ("gpt-4", "cl100k_base"),
+            ("code-davinci-002", "p50k_base"),
+            ("davinci", "r50k_base"),
+        ] {
+            assert_eq!(EncodingCache::model_to_encoding(model), encoding_type);
+            let size = ordinary_vocab_size(encoding_type);
+            assert!(counter.decode_single(0, model).is_ok(), "{}", model);
+            assert!(counter.decode_single(size - 1, model).is_ok(), "{}", model);
+
+            // Every special token decodes to its own name
+            let specials: Vec<(String, u32)> = counter
+                .with_encoding(model, |encoding| {
+                    encoding
+                        .special_tokens()
+                        .into_iter()
+                        .map(|name| {
+                            (
+                                name.to_string(),
+                                encoding.encode_with_special_tokens(name)[0],
+                            )
+                        })
+                        .collect()
+                })
+                .unwrap();
+            for (name, id) in &specials {
+                assert_eq!(counter.decode_single(*id, model).unwrap(), name.as_bytes());
+            }
+
+            // Gaps between the ordinary ranks and the specials are rejected, not panicked on
+            let gap = (size..size + 64).find(|id| specials.iter().all(|(_, s)| s != id));
+            if let Some(id) = gap {
+                assert!(
+                    counter.decode_single(id, model).is_err(),
+                    "{} {}",
+                    model,
+                    id
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_max_encode_bytes_boundary_on_every_path() {
+        let counter = TokenCounter::new();
+        let model = "gpt-4";
+        let text = "a".repeat(64);
+        let other = format!("{}b", "a".repeat(63));
+        let messages = vec![ChatMessage {
+            role: "user".to_string(),
+            content: text.clone(),
+            name: None,
+        }];
+        let message_bytes = "user".len() + text.len();
+
+        // At exactly the limit everything still goes through the encoder
+        counter.set_max_encode_bytes(Some(text.len()));
+        let exact_prefix = counter.common_prefix_tokens(&text, &other, model).unwrap();
+        let exact_suffix = counter.count_with_prefix(5, &text, model).unwrap();
+        assert!(counter.benchmark_encode(&text, model, 1).is_ok());
+        counter.set_max_encode_bytes(Some(message_bytes));
+        let exact_messages = counter
+            .count_message_tokens(&messages, Some(model))
+            .unwrap();
+
+        // One byte over, each path falls back (or refuses, for the benchmark)
+        counter.set_max_encode_bytes(Some(text.len() - 1));
+        assert_eq!(
+            counter.common_prefix_tokens(&text, &other, model).unwrap(),
+            counter.count_tokens_approx(&text[..63], Some(model))
+        );
+        assert_ne!(
+            counter.common_prefix_tokens(&text, &other, model).unwrap(),
+            exact_prefix
+        );
+        assert_eq!(
+            counter.count_with_prefix(5, &text, model).unwrap(),
+            5 + counter.count_tokens_approx(&text, Some(model))
+        );
+        assert_ne!(
+            counter.count_with_prefix(5, &text, model).unwrap(),
+            exact_suffix
+        );
+        let err = counter.benchmark_encode(&text, model, 1).unwrap_err();
+        assert!(err.contains("max_encode_bytes"), "{}", err);
+
+        counter.set_max_encode_bytes(Some(message_bytes - 1));
+        let approx_messages = counter
+            .count_message_tokens(&messages, Some(model))
+            .unwrap();
+        assert_eq!(
+            approx_messages,
+            exact_messages - counter.count_tokens(&text, Some(model)).unwrap()
+                + counter.count_tokens_approx(&text, Some(model))
+        );
+        assert_ne!(approx_messages, exact_messages);
+    }
 }
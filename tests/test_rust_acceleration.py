@@ -77,6 +77,411 @@ class TestRustFunctions:
         assert isinstance(stats, dict), "Performance stats should return dict"
         print(f"Performance stats: {stats}")
 
+    def test_dump_state(self):
+        """dump_state is valid JSON with a section per global component"""
+        import json
+
+        from fast_litellm._rust import AdvancedRouter, dump_state
+
+        AdvancedRouter().add_route("py-dump", {"endpoints": ["py-dump-a"]})
+        state = json.loads(dump_state())
+
+        for section in (
+            "timestamp_ms",
+            "rate_limiter",
+            "connection_pool",
+            "router",
+            "pricing",
+            "feature_flags",
+            "performance",
+        ):
+            assert section in state, section
+        assert state["router"]["routes"]["py-dump"]["endpoints"] == ["py-dump-a"]
+        assert "metrics" in state["router"]
+        assert json.loads(json.dumps(state)) == state
+
+    def test_cost_from_usage(self):
+        """Usage dicts and objects give the same cost; absent fields count as 0"""
+        from types import SimpleNamespace
+
+        from fast_litellm._rust import cost_from_usage
+
+        model = "gpt-3.5-turbo"
+        usage = {"prompt_tokens": 1000, "completion_tokens": 500}
+        cost = cost_from_usage(model, usage)
+        assert cost > 0
+        assert cost_from_usage(model, SimpleNamespace(**usage)) == cost
+        assert cost_from_usage(model, {}) == 0.0
+        assert cost_from_usage(
+            model, {"prompt_tokens": 1000, "completion_tokens": None}
+        ) == cost_from_usage(model, {"prompt_tokens": 1000})
+
+        cached = dict(usage, prompt_tokens_details={"cached_tokens": 400})
+        assert cost_from_usage(model, cached) <= cost
+        assert cost_from_usage(model, dict(usage, prompt_tokens_details=None)) == cost
+        # Cached tokens can't exceed the prompt
+        assert cost_from_usage(
+            model, dict(usage, prompt_tokens_details={"cached_tokens": 5000})
+        ) == cost_from_usage(
+            model, dict(usage, prompt_tokens_details={"cached_tokens": 1000})
+        )
+
+
+    def test_rate_limit_with_level(self):
+        """Usage level climbs as a key approaches its limit"""
+        from fast_litellm._rust import SimpleRateLimiter
+
+        limiter = SimpleRateLimiter(600)
+        results = [
+            limiter.check_rate_limit_with_level("py-level-key", 10) for _ in range(11)
+        ]
+        assert results[0]["allowed"] and results[0]["level"] == "ok"
+        assert results[0]["current_count"] == 1
+        assert results[9]["allowed"] and results[9]["level"] != "ok"
+        assert not results[10]["allowed"]
+
+
+class TestAdvancedRouter:
+    """Test routes, health and metrics through the Rust router"""
+
+    def test_route_and_cooldown(self):
+        """Routes pick healthy endpoints and name cooldown reasons on failure"""
+        from fast_litellm._rust import AdvancedRouter
+
+        router = AdvancedRouter()
+        router.add_route(
+            "py-chat", {"strategy": "least_busy", "endpoints": ["py-a", "py-b"]}
+        )
+
+        assert router.route("py-chat") == "py-a"
+        assert router.route_with_reason("py-chat") == ("py-a", "least-busy")
+        assert router.route_with_reason("py-chat", exclude=["py-a"]) == (
+            "py-b",
+            "only-healthy",
+        )
+        assert router.route("py-chat", exclude=["py-a"]) == "py-b"
+        with pytest.raises(ValueError):
+            router.route("py-chat", exclude=["py-a", "py-b"])
+
+        router.mark_endpoint_unhealthy("py-a", "timeout")
+        router.mark_endpoint_unhealthy("py-b")
+        with pytest.raises(ValueError, match="py-a=timeout"):
+            router.route("py-chat")
+        assert router.get_metrics()["py-b"]["cooldown_reason"] == "manual"
+
+        router.mark_endpoint_healthy("py-a")
+        router.mark_endpoint_healthy("py-b")
+
+    def test_required_model_info(self):
+        """Only deployments whose model_info has every required value are picked"""
+        from fast_litellm._rust import AdvancedRouter, get_available_deployment
+
+        router = AdvancedRouter()
+        us = {"model_name": "py-gpt", "model_info": {"id": "us", "region": "us"}}
+        eu = {
+            "model_name": "py-gpt",
+            "model_info": {"id": "eu", "region": "eu", "supports_streaming": True},
+        }
+        bare = {"model_name": "py-gpt"}
+        deployments = [us, eu, bare]
+
+        for _ in range(10):
+            picked = router.get_available_deployment(
+                deployments, "py-gpt", required_model_info={"region": "eu"}
+            )
+            assert picked["model_info"]["id"] == "eu"
+        assert (
+            router.get_available_deployment(
+                deployments,
+                "py-gpt",
+                required_model_info={"region": "us", "supports_streaming": True},
+            )
+            is None
+        )
+        assert router.get_available_deployment(
+            deployments, "py-gpt", required_model_info={}
+        ) in deployments
+        assert router.get_available_deployment(deployments, "py-gpt") in deployments
+
+        # The same filters apply on both entry points, together with exclude_ids
+        assert (
+            router.get_available_deployment(
+                deployments,
+                "py-gpt",
+                required_model_info={"region": "eu"},
+                exclude_ids=["eu"],
+            )
+            is None
+        )
+        for _ in range(10):
+            picked = get_available_deployment(
+                deployments,
+                "py-gpt",
+                exclude_ids=["us"],
+                required_model_info={"supports_streaming": True},
+            )
+            assert picked["model_info"]["id"] == "eu"
+
+    def test_unknown_route_and_bad_config(self):
+        """Unknown routes and malformed configs raise ValueError"""
+        from fast_litellm._rust import AdvancedRouter
+
+        router = AdvancedRouter()
+        with pytest.raises(ValueError, match="not found"):
+            router.route("py-missing")
+        with pytest.raises(ValueError, match="Invalid route config"):
+            router.add_route("py-bad", {"endpoints": "not-a-list"})
+
+    def test_metrics(self):
+        """Recorded requests show up in metrics"""
+        from fast_litellm._rust import AdvancedRouter
+
+        router = AdvancedRouter()
+        router.add_route("py-metrics", {"endpoints": ["py-m"]})
+        router.update_metrics("py-m", 120.0, True, 0.01)
+
+        metrics = router.get_metrics()["py-m"]
+        assert metrics["latency_ms"] == 120.0
+        assert metrics["latency_samples"] == 1
+
+    def test_route_strategy_is_not_per_instance(self):
+        """Routes share one router, so an instance's strategy isn't a route default"""
+        from fast_litellm._rust import AdvancedRouter
+
+        AdvancedRouter(strategy="least_busy").add_route(
+            "py-shared", {"endpoints": ["py-s1", "py-s2"]}
+        )
+        AdvancedRouter(strategy="latency_based").add_route(
+            "py-shared-2", {"endpoints": ["py-s1", "py-s2"]}
+        )
+        import json
+
+        routes = json.loads(fast_litellm._rust.dump_state())["router"]["routes"]
+        assert routes["py-shared"]["strategy"] == "simple_shuffle"
+        assert routes["py-shared-2"]["strategy"] == "simple_shuffle"
+
+    def test_route_within_budget(self):
+        """A larger max_tokens rules out the expensive endpoint"""
+        from fast_litellm._rust import AdvancedRouter
+
+        router = AdvancedRouter()
+        router.add_route(
+            "py-budget",
+            {
+                "strategy": "least_busy",
+                "endpoints": ["py-big", "py-small"],
+                "models": ["test-gpt-4-big", "test-gpt-3.5-small"],
+            },
+        )
+        assert router.route_within_budget("py-budget", 0.01, 100, max_tokens=50) == "py-big"
+        assert (
+            router.route_within_budget("py-budget", 0.01, 100, max_tokens=1000)
+            == "py-small"
+        )
+        with pytest.raises(ValueError, match="request budget"):
+            router.route_within_budget("py-budget", 0.001, 100, max_tokens=1000)
+
+
+    def test_team_usage(self):
+        """Tokens recorded with a team show up in that team's usage"""
+        from fast_litellm._rust import AdvancedRouter
+
+        router = AdvancedRouter()
+        router.add_route("py-teams", {"endpoints": ["py-t"]})
+        router.update_metrics("py-t", 120.0, True, 0.01, tokens=50, team="search")
+        router.update_metrics("py-t", 80.0, True, 0.01, tokens=30, team="search")
+        router.update_metrics("py-t", 90.0, True, 0.01)
+
+        assert router.get_team_usage("py-t") == {
+            "search": {"tokens": 80, "requests": 2}
+        }
+        assert router.get_team_usage("py-unknown") == {}
+
+    def test_circuit_breaker(self):
+        """Sustained failures open the breaker and route around the endpoint"""
+        from fast_litellm._rust import AdvancedRouter
+
+        router = AdvancedRouter()
+        router.add_route("py-breaker", {"endpoints": ["py-flaky", "py-steady"]})
+        for _ in range(10):
+            router.record_attempt("py-flaky", False)
+
+        metrics = router.get_metrics()
+        assert metrics["py-flaky"]["breaker_state"] == "open"
+        assert metrics["py-flaky"]["breaker_failure_rate"] == 1.0
+        for _ in range(10):
+            assert router.route("py-breaker") == "py-steady"
+
+    def test_adaptive_weights(self):
+        """Adaptive routes shift weight towards the faster endpoint"""
+        from fast_litellm._rust import AdvancedRouter
+
+        router = AdvancedRouter()
+        router.add_route(
+            "py-adaptive",
+            {
+                "strategy": "weighted_shuffle",
+                "endpoints": ["py-fast", "py-slow"],
+                "adaptive_weights": True,
+            },
+        )
+        for _ in range(50):
+            router.update_metrics("py-fast", 100.0, True, 0.0)
+            router.update_metrics("py-slow", 300.0, True, 0.0)
+
+        weights = router.get_adaptive_weights()["py-adaptive"]
+        assert weights["py-fast"] > 1.0 > weights["py-slow"]
+
+    def test_compare_strategies(self):
+        """Every strategy reports its pick for the route"""
+        from fast_litellm._rust import AdvancedRouter
+
+        router = AdvancedRouter()
+        router.add_route("py-compare", {"endpoints": ["py-c1", "py-c2"]})
+        picks = router.compare_strategies("py-compare")
+
+        assert set(picks) == {
+            "simple_shuffle",
+            "least_busy",
+            "latency_based",
+            "cost_based",
+            "weighted_shuffle",
+        }
+        assert all(pick in ("py-c1", "py-c2") for pick in picks.values())
+        assert picks["least_busy"] == "py-c1"
+        with pytest.raises(ValueError, match="not found"):
+            router.compare_strategies("py-missing")
+
+    def test_reset_stats(self):
+        """reset_stats clears team usage but keeps warm averages"""
+        from fast_litellm._rust import AdvancedRouter
+
+        router = AdvancedRouter()
+        router.add_route("py-reset", {"endpoints": ["py-r"]})
+        router.update_metrics("py-r", 200.0, True, 0.01, tokens=10, team="ops")
+        router.mark_endpoint_unhealthy("py-r", "timeout")
+
+        router.reset_stats(preserve_health=True)
+        metrics = router.get_metrics()["py-r"]
+        assert metrics["latency_ms"] == 200.0
+        assert metrics["latency_samples"] == 1
+        assert metrics["cooldown_reason"] == "timeout"
+        assert router.get_team_usage("py-r") == {}
+
+        router.mark_endpoint_healthy("py-r")
+
+    def test_rebalance_hint(self):
+        """rebalance_hint drops tuned weights but keeps metrics"""
+        from fast_litellm._rust import AdvancedRouter
+
+        router = AdvancedRouter()
+        router.add_route(
+            "py-rebalance",
+            {
+                "strategy": "weighted_shuffle",
+                "endpoints": ["py-rb1", "py-rb2"],
+                "adaptive_weights": True,
+            },
+        )
+        router.update_metrics("py-rb1", 100.0, True, 0.0)
+        router.update_metrics("py-rb2", 300.0, True, 0.0)
+        assert "py-rebalance" in router.get_adaptive_weights()
+
+        router.rebalance_hint()
+        assert "py-rebalance" not in router.get_adaptive_weights()
+        assert router.get_metrics()["py-rb1"]["latency_samples"] == 1
+
+    def test_route_with_cost_ceiling(self):
+        """Only endpoints under the output price ceiling are picked"""
+        from fast_litellm._rust import AdvancedRouter
+
+        router = AdvancedRouter()
+        router.add_route(
+            "py-ceiling",
+            {
+                "endpoints": ["py-pricey", "py-cheap"],
+                "models": ["test-gpt-4-pricey", "test-gpt-3.5-cheap"],
+            },
+        )
+        for _ in range(10):
+            assert router.route_with_cost_ceiling("py-ceiling", 10.0) == "py-cheap"
+        with pytest.raises(ValueError, match="exceed"):
+            router.route_with_cost_ceiling("py-ceiling", 1.0)
+
+    def test_set_cost_ratio(self):
+        """A family's token mix changes which list price ranks cheapest"""
+        from fast_litellm._rust import AdvancedRouter
+
+        router = AdvancedRouter()
+        router.add_route(
+            "py-ratio",
+            {
+                "strategy": "cost_based",
+                "endpoints": ["py-gpt", "py-claude"],
+                "models": ["py-fam-gpt-4-x", "py-fam-claude-x"],
+            },
+        )
+        # Default 3:1 input-heavy mix favours the cheaper input price
+        assert router.route("py-ratio") == "py-claude"
+        router.set_cost_ratio("py-fam-", 0.5)
+        assert router.route("py-ratio") == "py-gpt"
+
+    def test_replace_routes(self):
+        """A reload swaps the route set and keeps state for kept endpoints"""
+        import json
+
+        from fast_litellm._rust import AdvancedRouter, dump_state
+
+        router = AdvancedRouter()
+        router.add_route("py-old", {"endpoints": ["py-keep", "py-drop"]})
+        router.update_metrics("py-keep", 50.0, True, 0.0)
+        router.update_metrics("py-drop", 50.0, True, 0.0)
+
+        carried_over, new = router.replace_routes(
+            {"py-new": {"endpoints": ["py-keep", "py-fresh"]}}
+        )
+        assert (carried_over, new) == (1, 1)
+        assert set(json.loads(dump_state())["router"]["routes"]) == {"py-new"}
+        metrics = router.get_metrics()
+        assert metrics["py-keep"]["latency_samples"] == 1
+        assert "py-drop" not in metrics
+        with pytest.raises(ValueError, match="not found"):
+            router.route("py-old")
+
+    def test_warmup_factors(self):
+        """Newly added endpoints on a warm-up route start below full weight"""
+        from fast_litellm._rust import AdvancedRouter
+
+        router = AdvancedRouter()
+        router.add_route(
+            "py-warm",
+            {
+                "strategy": "weighted_shuffle",
+                "endpoints": ["py-w1", "py-w2"],
+                "warmup_seconds": 60,
+            },
+        )
+
+        factors = router.get_warmup_factors()["py-warm"]
+        assert set(factors) == {"py-w1", "py-w2"}
+        assert all(0.0 < factor <= 1.0 for factor in factors.values())
+        assert "py-chat" not in router.get_warmup_factors()
+
+    def test_routing_seed_makes_routes_reproducible(self):
+        """set_routing_seed also seeds the router's shuffle strategies"""
+        from fast_litellm._rust import AdvancedRouter, set_routing_seed
+
+        router = AdvancedRouter()
+        router.add_route(
+            "py-seeded", {"endpoints": ["py-s1", "py-s2", "py-s3", "py-s4"]}
+        )
+        try:
+            set_routing_seed(7)
+            first = [router.route("py-seeded") for _ in range(20)]
+            set_routing_seed(7)
+            assert [router.route("py-seeded") for _ in range(20)] == first
+        finally:
+            set_routing_seed(None)
 
 # Check if litellm is available and compatible with this Python version
 try: